@@ -1,7 +1,6 @@
 //! Serialize and deserialize a StackAddr using serde_json.
 
 use bytes::Bytes;
-use serde_json;
 use stackaddr::{Identity, Protocol, StackAddr};
 
 fn main() {
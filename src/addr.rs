@@ -1,27 +1,36 @@
 use bytes::Bytes;
 use mac_addr::MacAddr;
-#[cfg(feature = "serde")]
-use serde::{Deserialize, Serialize};
 
 use crate::{
+    borrowed::SegmentRef,
+    codec,
+    display::{DisplayOptions, StackAddrDisplay},
     error::StackAddrError,
+    parse::{ParseOptions, TextSegments},
+    registry::ProtocolRegistry,
+    resolver::{AddrFamily, AsyncResolver, Resolver, SystemResolver},
     segment::{
         Segment,
         identity::Identity,
-        protocol::{Protocol, TransportProtocol},
+        protocol::{Layer, Protocol, TransportKind, TransportProtocol},
     },
+    validate::{self, Violation},
 };
 use std::{
-    fmt, io,
-    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs},
+    fmt, io, mem,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6, ToSocketAddrs},
+    ops::{Add, AddAssign},
+    path::{Path, PathBuf},
     str::FromStr,
 };
 use uuid::Uuid;
 
 /// A stack address that contains a stack of protocols.
 /// The stack address can be used to represent a network address with multiple protocols.
+///
+/// With the `serde` feature, it serializes as its string form for human-readable
+/// formats and as its binary form otherwise.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct StackAddr {
     segments: Vec<Segment>,
 }
@@ -91,9 +100,11 @@ impl StackAddr {
 
     /// Create a new `StackAddr` with a MAC address segment from a string.
     pub fn try_with_mac_str(mut self, addr: &str) -> Result<Self, StackAddrError> {
-        let mac: MacAddr = addr
-            .parse()
-            .map_err(|_e| StackAddrError::InvalidEncoding("mac"))?;
+        let mac: MacAddr = addr.parse().map_err(|_e| StackAddrError::InvalidValue {
+            protocol: "mac".to_string(),
+            raw: addr.to_string(),
+            reason: "invalid MAC address".to_string(),
+        })?;
         self.segments.push(Segment::Protocol(Protocol::Mac(mac)));
         Ok(self)
     }
@@ -146,6 +157,39 @@ impl StackAddr {
         self
     }
 
+    /// Create a new `StackAddr` with a Unix domain socket path segment.
+    /// This is a convenience method for creating a stack address with builder pattern.
+    pub fn with_unix_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.segments
+            .push(Segment::Protocol(Protocol::Unix(path.into())));
+        self
+    }
+
+    /// Create a new `StackAddr` with the segments of a transport protocol, e.g. `/udp/443/quic`.
+    /// This is a convenience method for creating a stack address with builder pattern.
+    pub fn with_transport(mut self, transport: TransportProtocol) -> Self {
+        self.segments
+            .extend(transport.protocols().into_iter().map(Segment::Protocol));
+        self
+    }
+
+    /// Create a new `StackAddr` from a socket address and a transport.
+    ///
+    /// A non-zero IPv6 scope id is kept as a numeric zone, e.g.
+    /// `/ip6zone/2/ip6/fe80::1/udp/4433/quic` for a QUIC socket address.
+    pub fn from_socket_addr(addr: SocketAddr, kind: TransportKind) -> Self {
+        let mut out = StackAddr::empty();
+        if let SocketAddr::V6(v6) = addr
+            && v6.scope_id() != 0
+        {
+            out.push(Segment::Protocol(Protocol::Ip6Zone(
+                v6.scope_id().to_string(),
+            )));
+        }
+        out.with_ip(addr.ip())
+            .with_transport(kind.with_port(addr.port()))
+    }
+
     /// Returns a reference to the ordered list of segments that make up this stack address.
     pub fn segments(&self) -> &[Segment] {
         &self.segments
@@ -161,6 +205,44 @@ impl StackAddr {
         self.segments.pop()
     }
 
+    /// Returns a new stack address with `inner` appended on top of this one.
+    pub fn encapsulate(&self, inner: &StackAddr) -> StackAddr {
+        let mut segments = self.segments.clone();
+        segments.extend_from_slice(&inner.segments);
+        StackAddr { segments }
+    }
+
+    /// Returns a new stack address with everything from the last occurrence of
+    /// `protocol` onwards removed.
+    ///
+    /// Protocols are matched by kind, ignoring their value, so `Protocol::Tcp(0)`
+    /// strips from the last `/tcp/<port>` whatever the port. Custom and registered
+    /// protocols are matched by name. If the protocol does not occur, the address is
+    /// returned unchanged.
+    pub fn decapsulate(&self, protocol: &Protocol) -> StackAddr {
+        self.truncate_at_last(|p| match (p, protocol) {
+            (Protocol::Custom(a), Protocol::Custom(b)) => a == b,
+            (Protocol::Registered { name: a, .. }, Protocol::Registered { name: b, .. }) => a == b,
+            (p, protocol) => mem::discriminant(p) == mem::discriminant(protocol),
+        })
+    }
+
+    /// Like [`StackAddr::decapsulate`], but matches protocols by their binary code.
+    pub fn decapsulate_code(&self, code: u64) -> StackAddr {
+        self.truncate_at_last(|p| p.code() == code)
+    }
+
+    fn truncate_at_last(&self, matches: impl Fn(&Protocol) -> bool) -> StackAddr {
+        let pos = self
+            .segments
+            .iter()
+            .rposition(|seg| matches!(seg, Segment::Protocol(p) if matches(p)));
+        match pos {
+            Some(pos) => StackAddr::from_parts(&self.segments[..pos]),
+            None => self.clone(),
+        }
+    }
+
     /// Check if the stack address contains a specific segment.
     pub fn contains(&self, target: &Segment) -> bool {
         self.segments.contains(target)
@@ -221,6 +303,59 @@ impl StackAddr {
             .collect()
     }
 
+    /// Returns the layer each segment is attributed to.
+    ///
+    /// Segments without a layer of their own (identities, paths, metadata, and custom
+    /// protocols) belong to the layer of the closest protocol before them, or to the
+    /// first protocol after them when they lead the stack.
+    fn segment_layers(&self) -> Vec<Option<Layer>> {
+        let mut layers: Vec<Option<Layer>> = Vec::with_capacity(self.segments.len());
+        let mut current = None;
+        for seg in &self.segments {
+            if let Segment::Protocol(p) = seg
+                && let Some(layer) = p.layer()
+            {
+                current = Some(layer);
+            }
+            layers.push(current);
+        }
+        let first = layers.iter().flatten().next().copied();
+        for layer in layers.iter_mut().take_while(|l| l.is_none()) {
+            *layer = first;
+        }
+        layers
+    }
+
+    /// Returns the sub-stack belonging to the given layer.
+    ///
+    /// Identity, path and metadata segments follow the layer of the protocol before them,
+    /// so `layer(Layer::Application)` of `/ip4/1.2.3.4/tcp/80/http/api` is `/http/api`.
+    pub fn layer(&self, layer: Layer) -> StackAddr {
+        let segments = self
+            .segments
+            .iter()
+            .zip(self.segment_layers())
+            .filter(|(_, l)| *l == Some(layer))
+            .map(|(seg, _)| seg.clone())
+            .collect();
+        StackAddr { segments }
+    }
+
+    /// Split the stack into the part below `layer` and the part from `layer` upwards.
+    ///
+    /// The split happens at the first segment attributed to `layer` or a higher layer.
+    /// For example, `split_at_layer(Layer::Network)` strips the link layer, and
+    /// `split_at_layer(Layer::Application).1` keeps only the application part.
+    pub fn split_at_layer(&self, layer: Layer) -> (StackAddr, StackAddr) {
+        let at = self
+            .segment_layers()
+            .iter()
+            .position(|l| l.is_some_and(|l| l >= layer))
+            .unwrap_or(self.segments.len());
+        let (lower, upper) = self.segments.split_at(at);
+        (StackAddr::from_parts(lower), StackAddr::from_parts(upper))
+    }
+
     /// Extract the transport protocol (if any) from the address.
     pub fn transport(&self) -> Option<TransportProtocol> {
         let mut port = None;
@@ -252,11 +387,8 @@ impl StackAddr {
     /// Get the MAC address from the stack address.
     pub fn mac(&self) -> Option<MacAddr> {
         for seg in &self.segments {
-            if let Segment::Protocol(p) = seg {
-                match p {
-                    Protocol::Mac(addr) => return Some(addr.clone()),
-                    _ => {}
-                }
+            if let Segment::Protocol(Protocol::Mac(addr)) = seg {
+                return Some(*addr);
             }
         }
         None
@@ -292,15 +424,64 @@ impl StackAddr {
         None
     }
 
+    /// Get the IPv6 zone from the stack address.
+    pub fn zone(&self) -> Option<&str> {
+        self.segments.iter().find_map(|seg| match seg {
+            Segment::Protocol(Protocol::Ip6Zone(zone)) => Some(zone.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Get the Unix domain socket path from the stack address.
+    ///
+    /// Linux abstract-namespace names are returned with their leading NUL byte.
+    pub fn unix_path(&self) -> Option<&Path> {
+        self.segments.iter().find_map(|seg| match seg {
+            Segment::Protocol(Protocol::Unix(path)) => Some(path.as_path()),
+            _ => None,
+        })
+    }
+
+    /// Returns the scope id of the IPv6 zone, or 0 without a zone.
+    fn scope_id_with<R: Resolver>(&self, resolver: &R) -> Result<u32, StackAddrError> {
+        match self.zone() {
+            None => Ok(0),
+            Some(zone) => match zone.parse() {
+                Ok(scope_id) => Ok(scope_id),
+                Err(_) => resolver.scope_id(zone),
+            },
+        }
+    }
+
+    /// Returns the scope id of a numeric IPv6 zone, or 0 without a zone.
+    ///
+    /// Interface names are rejected with [`StackAddrError::NoEquivalent`], since they
+    /// need a lookup.
+    fn numeric_scope_id(&self) -> Result<u32, StackAddrError> {
+        match self.zone() {
+            None => Ok(0),
+            Some(zone) => zone
+                .parse()
+                .map_err(|_| StackAddrError::NoEquivalent(format!("ip6 zone {}", zone))),
+        }
+    }
+
     /// Get the socket address from the stack address.
+    ///
+    /// A numeric `/ip6zone` sets the scope id of an IPv6 address. Zones naming an
+    /// interface return `None`; use [`StackAddr::socket_addrs_with`] to look them up.
     pub fn socket_addr(&self) -> Option<SocketAddr> {
         let ip = self.ip()?;
         let port = self.port()?;
-        Some(SocketAddr::new(ip, port))
+        let scope_id = self.numeric_scope_id().ok()?;
+        Some(scoped_socket_addr(ip, port, scope_id))
     }
 
     /// Get the host (IP or DNS) and port pair, returning an error when either is missing.
     pub fn host_port(&self) -> Result<(String, u16), StackAddrError> {
+        if self.is_empty() {
+            return Err(StackAddrError::EmptyAddress);
+        }
         let port = self
             .port()
             .ok_or(StackAddrError::MissingPart("transport port"))?;
@@ -321,25 +502,146 @@ impl StackAddr {
     /// This helper makes it easy to hand a `StackAddr` directly to networking libraries
     /// that expect socket addresses or types implementing [`ToSocketAddrs`]. It will
     /// return an error when host or port information is missing, or if DNS resolution
-    /// fails.
+    /// fails. Results are filtered to IPv4 for `/dns4` and to IPv6 for `/dns6`.
     pub fn socket_addrs(&self) -> Result<Vec<SocketAddr>, StackAddrError> {
-        let (host, port) = self.host_port()?;
-        (host.as_str(), port)
-            .to_socket_addrs()
-            .map_err(|e| StackAddrError::ResolutionFailed(e.to_string()))
-            .map(|iter| iter.collect())
+        self.socket_addrs_with(&SystemResolver)
+    }
+
+    /// Resolve the address into concrete [`SocketAddr`] values using `resolver`.
+    ///
+    /// Behaves like [`StackAddr::socket_addrs`], but with a caller-provided resolver,
+    /// which is also used to look up `/ip6zone` interface names.
+    pub fn socket_addrs_with<R: Resolver>(
+        &self,
+        resolver: &R,
+    ) -> Result<Vec<SocketAddr>, StackAddrError> {
+        if self.is_empty() {
+            return Err(StackAddrError::EmptyAddress);
+        }
+        let port = self
+            .port()
+            .ok_or(StackAddrError::MissingPart("transport port"))?;
+
+        if let Some(ip) = self.ip() {
+            let scope_id = self.scope_id_with(resolver)?;
+            return Ok(vec![scoped_socket_addr(ip, port, scope_id)]);
+        }
+
+        let (_, name, family) = self
+            .dns_lookup_target()
+            .ok_or(StackAddrError::MissingPart("ip or dns name"))?;
+        let addrs: Vec<SocketAddr> = resolver
+            .lookup(name, family)?
+            .into_iter()
+            .filter(|ip| family.matches(ip))
+            .map(|ip| SocketAddr::new(ip, port))
+            .collect();
+        if addrs.is_empty() {
+            return Err(StackAddrError::ResolutionFailed(format!(
+                "no {} addresses for {}",
+                family, name
+            )));
+        }
+        Ok(addrs)
+    }
+
+    /// Returns a value that writes the string form with `options`.
+    ///
+    /// Use this for human-facing output; `Display` always writes the canonical form.
+    pub fn display_with<'a>(&'a self, options: &DisplayOptions<'a>) -> StackAddrDisplay<'a> {
+        StackAddrDisplay::new(self, options)
+    }
+
+    /// Encode the stack address into its compact binary form.
+    ///
+    /// See the [`codec`] module for a description of the wire format.
+    pub fn to_bytes(&self) -> Bytes {
+        codec::encode(&self.segments, None)
+    }
+
+    /// Decode a stack address from the binary form produced by [`StackAddr::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, StackAddrError> {
+        Ok(StackAddr {
+            segments: codec::decode(bytes, None)?,
+        })
+    }
+
+    /// Encode the stack address, using the registered codes for user-defined protocols.
+    pub fn to_bytes_with(&self, registry: &ProtocolRegistry) -> Bytes {
+        codec::encode(&self.segments, Some(registry))
+    }
+
+    /// Decode a stack address that may contain registered protocol codes.
+    pub fn from_bytes_with(
+        registry: &ProtocolRegistry,
+        bytes: &[u8],
+    ) -> Result<Self, StackAddrError> {
+        Ok(StackAddr {
+            segments: codec::decode(bytes, Some(registry))?,
+        })
+    }
+
+    /// Check that the protocol stack is layered correctly.
+    ///
+    /// Returns every [`Violation`] found, such as a transport without a network layer,
+    /// `/quic` not over `/udp`, or an identity placed before the transport.
+    /// A network protocol after an identity or application protocol starts a new
+    /// stack, as in the result of [`StackAddr::encapsulate`], which is checked on its own.
+    pub fn validate(&self) -> Result<(), Vec<Violation>> {
+        let violations = validate::validate(&self.segments);
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+
+    /// Parse a stack address and reject it if the stack is not layered correctly.
+    ///
+    /// This is [`FromStr`] followed by [`StackAddr::validate`], so addresses built with
+    /// [`StackAddr::encapsulate`] are accepted as long as each stack is layered correctly.
+    /// An empty address is rejected with [`StackAddrError::EmptyAddress`].
+    pub fn parse_strict(s: &str) -> Result<Self, StackAddrError> {
+        let addr: StackAddr = s.parse()?;
+        if addr.is_empty() {
+            return Err(StackAddrError::EmptyAddress);
+        }
+        addr.validate().map_err(StackAddrError::InvalidStack)?;
+        Ok(addr)
+    }
+
+    /// Parse a stack address, recognising the protocols described by `registry`.
+    ///
+    /// Words that match a registered protocol produce [`Protocol::Registered`]
+    /// segments instead of paths. Built-in protocols are parsed as with [`FromStr`].
+    pub fn parse_with(registry: &ProtocolRegistry, s: &str) -> Result<Self, StackAddrError> {
+        StackAddr::parse_with_options(s, &ParseOptions::new().with_registry(registry))
+    }
+
+    /// Write the string form for [`StackAddr::parse_with`] with the same `registry`.
+    ///
+    /// Unlike `Display`, paths named like a registered protocol are escaped.
+    pub fn to_string_with(&self, registry: &ProtocolRegistry) -> String {
+        self.display_with(&DisplayOptions::new().with_registry(registry))
+            .to_string()
+    }
+
+    /// Parse a stack address with the given [`ParseOptions`].
+    pub fn parse_with_options(s: &str, options: &ParseOptions) -> Result<Self, StackAddrError> {
+        let segments = TextSegments::new(s, *options)
+            .map(|seg| seg.map(SegmentRef::into_owned))
+            .collect::<Result<_, _>>()?;
+        Ok(StackAddr { segments })
     }
 
     /// Get the DNS name from the stack address.
     pub fn name(&self) -> Option<&str> {
         for seg in &self.segments {
-            if let Segment::Protocol(p) = seg {
-                match p {
-                    Protocol::Dns(name) | Protocol::Dns4(name) | Protocol::Dns6(name) => {
-                        return Some(name);
-                    }
-                    _ => {}
-                }
+            if let Segment::Protocol(
+                Protocol::Dns(name) | Protocol::Dns4(name) | Protocol::Dns6(name),
+            ) = seg
+            {
+                return Some(name);
             }
         }
         None
@@ -394,9 +696,179 @@ impl StackAddr {
         None
     }
 
+    /// Returns all identity segments, in order.
+    ///
+    /// Relay addresses carry more than one, e.g. the relay node followed by the
+    /// destination peer.
+    pub fn identities(&self) -> Vec<&Identity> {
+        self.segments
+            .iter()
+            .filter_map(|seg| {
+                if let Segment::Identity(id) = seg {
+                    Some(id)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the last identity segment if present, i.e. the final destination.
+    pub fn last_identity(&self) -> Option<&Identity> {
+        self.segments.iter().rev().find_map(|seg| match seg {
+            Segment::Identity(id) => Some(id),
+            _ => None,
+        })
+    }
+
+    /// Returns the bytes of the first `/node` identity if present.
+    pub fn node_id(&self) -> Option<&Bytes> {
+        self.segments.iter().find_map(|seg| match seg {
+            Segment::Identity(Identity::NodeId(id)) => Some(id),
+            _ => None,
+        })
+    }
+
+    /// Returns the bytes of the first `/peer` identity if present.
+    pub fn peer_id(&self) -> Option<&Bytes> {
+        self.segments.iter().find_map(|seg| match seg {
+            Segment::Identity(Identity::PeerId(id)) => Some(id),
+            _ => None,
+        })
+    }
+
+    /// Returns the first `/uuid` identity if present.
+    pub fn uuid(&self) -> Option<Uuid> {
+        self.segments.iter().find_map(|seg| match seg {
+            Segment::Identity(Identity::Uuid(uuid)) => Some(*uuid),
+            _ => None,
+        })
+    }
+
+    /// Returns a new stack address with all identity segments removed.
+    pub fn without_identities(&self) -> StackAddr {
+        StackAddr {
+            segments: self
+                .segments
+                .iter()
+                .filter(|seg| !matches!(seg, Segment::Identity(_)))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Replace the last identity segment (the destination) with `identity`.
+    /// If there is no identity yet, `identity` is appended.
+    /// This is a convenience method for rewriting a stack address with builder pattern.
+    pub fn with_identity_replaced(mut self, identity: Identity) -> Self {
+        let pos = self
+            .segments
+            .iter()
+            .rposition(|seg| matches!(seg, Segment::Identity(_)));
+        match pos {
+            Some(pos) => self.segments[pos] = Segment::Identity(identity),
+            None => self.segments.push(Segment::Identity(identity)),
+        }
+        self
+    }
+
+    /// Check if both addresses point at the same endpoint identity.
+    ///
+    /// Compares the first identity of each address with [`Identity::same_key`].
+    /// Returns `false` if either address has no identity.
+    pub fn same_identity(&self, other: &StackAddr) -> bool {
+        match (self.get_identity(), other.get_identity()) {
+            (Some(a), Some(b)) => a.same_key(b),
+            _ => false,
+        }
+    }
+
+    /// Returns the index, name and address family of the first DNS segment.
+    fn dns_lookup_target(&self) -> Option<(usize, &str, AddrFamily)> {
+        self.segments
+            .iter()
+            .enumerate()
+            .find_map(|(i, seg)| match seg {
+                Segment::Protocol(
+                    p @ (Protocol::Dns(name) | Protocol::Dns4(name) | Protocol::Dns6(name)),
+                ) => Some((i, name.as_str(), AddrFamily::of(p)?)),
+                _ => None,
+            })
+    }
+
+    /// Builds one resolved address per IP, replacing the DNS segment at `index`.
+    fn with_resolved_ips(
+        &self,
+        index: usize,
+        name: &str,
+        family: AddrFamily,
+        ips: Vec<IpAddr>,
+    ) -> Result<Vec<StackAddr>, StackAddrError> {
+        let addrs: Vec<StackAddr> = ips
+            .into_iter()
+            .filter(|ip| family.matches(ip))
+            .map(|ip| {
+                let mut addr = self.clone();
+                addr.segments[index] = Segment::Protocol(match ip {
+                    IpAddr::V4(v4) => Protocol::Ip4(v4),
+                    IpAddr::V6(v6) => Protocol::Ip6(v6),
+                });
+                addr
+            })
+            .collect();
+        if addrs.is_empty() {
+            return Err(StackAddrError::ResolutionFailed(format!(
+                "no {} addresses for {}",
+                family, name
+            )));
+        }
+        Ok(addrs)
+    }
+
+    /// Resolve the DNS segment with `resolver`, returning one address per resolved IP.
+    ///
+    /// `/dns4` only yields IPv4 addresses and `/dns6` only IPv6 addresses. An address
+    /// without a DNS segment is returned as-is.
+    pub fn resolve_with<R: Resolver>(
+        &self,
+        resolver: &R,
+    ) -> Result<Vec<StackAddr>, StackAddrError> {
+        let Some((index, name, family)) = self.dns_lookup_target() else {
+            return Ok(vec![self.clone()]);
+        };
+        let ips = resolver.lookup(name, family)?;
+        self.with_resolved_ips(index, name, family, ips)
+    }
+
+    /// Asynchronous version of [`StackAddr::resolve_with`].
+    pub async fn resolve_with_async<R: AsyncResolver>(
+        &self,
+        resolver: &R,
+    ) -> Result<Vec<StackAddr>, StackAddrError> {
+        let Some((index, name, family)) = self.dns_lookup_target() else {
+            return Ok(vec![self.clone()]);
+        };
+        let ips = resolver.lookup(name, family).await?;
+        self.with_resolved_ips(index, name, family, ips)
+    }
+
     /// Replace Dns/Dns4/Dns6 protocol with Ip4 or Ip6
     /// This is used to resolve the name to an IP address
-    pub fn resolve(&mut self, ip_addr: IpAddr) {
+    ///
+    /// Returns [`StackAddrError::FamilyMismatch`] and leaves the address unchanged
+    /// if `ip_addr` is IPv6 for a `/dns4` segment, or IPv4 for a `/dns6` segment.
+    pub fn resolve(&mut self, ip_addr: IpAddr) -> Result<(), StackAddrError> {
+        for seg in &self.segments {
+            if let Segment::Protocol(p) = seg
+                && let Some(family) = AddrFamily::of(p)
+                && !family.matches(&ip_addr)
+            {
+                return Err(StackAddrError::FamilyMismatch {
+                    protocol: p.clone(),
+                    ip: ip_addr,
+                });
+            }
+        }
         for seg in &mut self.segments {
             if let Segment::Protocol(p) = seg {
                 match p {
@@ -410,6 +882,15 @@ impl StackAddr {
                 }
             }
         }
+        Ok(())
+    }
+}
+
+/// Builds a socket address, applying `scope_id` to IPv6 addresses.
+fn scoped_socket_addr(ip: IpAddr, port: u16, scope_id: u32) -> SocketAddr {
+    match ip {
+        IpAddr::V4(ip) => SocketAddr::V4(SocketAddrV4::new(ip, port)),
+        IpAddr::V6(ip) => SocketAddr::V6(SocketAddrV6::new(ip, port, 0, scope_id)),
     }
 }
 
@@ -426,147 +907,193 @@ impl FromStr for StackAddr {
     type Err = StackAddrError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut segments = Vec::new();
-        let mut parts = s.split('/').filter(|p| !p.is_empty());
-
-        while let Some(part) = parts.next() {
-            let seg = match part {
-                "ip4" => Segment::Protocol(Protocol::Ip4(
-                    parts
-                        .next()
-                        .ok_or(StackAddrError::MissingPart("ip4 address"))?
-                        .parse()?,
-                )),
-                "ip6" => Segment::Protocol(Protocol::Ip6(
-                    parts
-                        .next()
-                        .ok_or(StackAddrError::MissingPart("ip6 address"))?
-                        .parse()?,
-                )),
-                "dns" => Segment::Protocol(Protocol::Dns(
-                    parts
-                        .next()
-                        .ok_or(StackAddrError::MissingPart("dns"))?
-                        .to_string(),
-                )),
-                "dns4" => Segment::Protocol(Protocol::Dns4(
-                    parts
-                        .next()
-                        .ok_or(StackAddrError::MissingPart("dns4"))?
-                        .to_string(),
-                )),
-                "dns6" => Segment::Protocol(Protocol::Dns6(
-                    parts
-                        .next()
-                        .ok_or(StackAddrError::MissingPart("dns6"))?
-                        .to_string(),
-                )),
-                "mac" => Segment::Protocol(Protocol::Mac(
-                    parts
-                        .next()
-                        .ok_or(StackAddrError::MissingPart("mac address"))?
-                        .parse()
-                        .map_err(|_e| StackAddrError::InvalidEncoding("mac"))?,
-                )),
-                "tcp" => Segment::Protocol(Protocol::Tcp(
-                    parts
-                        .next()
-                        .ok_or(StackAddrError::MissingPart("tcp port"))?
-                        .parse()?,
-                )),
-                "udp" => Segment::Protocol(Protocol::Udp(
-                    parts
-                        .next()
-                        .ok_or(StackAddrError::MissingPart("udp port"))?
-                        .parse()?,
-                )),
-                "tls" => Segment::Protocol(Protocol::Tls),
-                "quic" => Segment::Protocol(Protocol::Quic),
-                "http" => Segment::Protocol(Protocol::Http),
-                "https" => Segment::Protocol(Protocol::Https),
-                "ws" => Segment::Protocol(Protocol::Ws(
-                    parts
-                        .next()
-                        .ok_or(StackAddrError::MissingPart("ws port"))?
-                        .parse()?,
-                )),
-                "wss" => Segment::Protocol(Protocol::Wss(
-                    parts
-                        .next()
-                        .ok_or(StackAddrError::MissingPart("wss port"))?
-                        .parse()?,
-                )),
-                "wtr" => Segment::Protocol(Protocol::WebTransport(
-                    parts
-                        .next()
-                        .ok_or(StackAddrError::MissingPart("wtr port"))?
-                        .parse()?,
-                )),
-                "webrtc" => Segment::Protocol(Protocol::WebRTC),
-                "onion" => Segment::Protocol(Protocol::Onion(
-                    parts
-                        .next()
-                        .ok_or(StackAddrError::MissingPart("onion address"))?
-                        .to_string(),
-                )),
-                "custom" => Segment::Protocol(Protocol::Custom(
-                    parts
-                        .next()
-                        .ok_or(StackAddrError::MissingPart("custom name"))?
-                        .to_string(),
-                )),
-                "node" => {
-                    let encoded = parts.next().ok_or(StackAddrError::MissingPart("node id"))?;
-                    let decoded =
-                        base32::decode(base32::Alphabet::Rfc4648 { padding: false }, encoded)
-                            .ok_or(StackAddrError::InvalidEncoding("base32 node id"))?;
-                    Segment::Identity(Identity::NodeId(Bytes::from(decoded)))
-                }
-                "peer" => {
-                    let encoded = parts.next().ok_or(StackAddrError::MissingPart("peer id"))?;
-                    let decoded =
-                        base32::decode(base32::Alphabet::Rfc4648 { padding: false }, encoded)
-                            .ok_or(StackAddrError::InvalidEncoding("base32 peer id"))?;
-                    Segment::Identity(Identity::PeerId(Bytes::from(decoded)))
-                }
-                "uuid" => {
-                    let val = parts
-                        .next()
-                        .ok_or(StackAddrError::MissingPart("uuid value"))?;
-                    let uuid = Uuid::parse_str(val)
-                        .map_err(|_| StackAddrError::InvalidEncoding("uuid"))?;
-                    Segment::Identity(Identity::Uuid(uuid))
-                }
-                "identity" => {
-                    let kind = parts
-                        .next()
-                        .ok_or(StackAddrError::MissingPart("identity kind"))?;
-                    let encoded = parts
-                        .next()
-                        .ok_or(StackAddrError::MissingPart("identity value"))?;
-                    let decoded =
-                        base32::decode(base32::Alphabet::Rfc4648 { padding: false }, encoded)
-                            .ok_or(StackAddrError::InvalidEncoding("base32 identity"))?;
-                    Segment::Identity(Identity::Custom {
-                        kind: kind.to_string(),
-                        id: Bytes::from(decoded),
-                    })
-                }
-                "meta" => {
-                    let k = parts
-                        .next()
-                        .ok_or(StackAddrError::MissingPart("metadata key"))?;
-                    let v = parts
-                        .next()
-                        .ok_or(StackAddrError::MissingPart("metadata value"))?;
-                    Segment::Metadata(k.to_string(), v.to_string())
-                }
-                s => Segment::Path(s.to_string()),
-            };
-            segments.push(seg);
+        StackAddr::parse_with_options(s, &ParseOptions::new())
+    }
+}
+
+impl Add for StackAddr {
+    type Output = StackAddr;
+
+    fn add(mut self, rhs: StackAddr) -> StackAddr {
+        self.segments.extend(rhs.segments);
+        self
+    }
+}
+
+impl Add<&StackAddr> for StackAddr {
+    type Output = StackAddr;
+
+    fn add(mut self, rhs: &StackAddr) -> StackAddr {
+        self.segments.extend_from_slice(&rhs.segments);
+        self
+    }
+}
+
+impl AddAssign for StackAddr {
+    fn add_assign(&mut self, rhs: StackAddr) {
+        self.segments.extend(rhs.segments);
+    }
+}
+
+impl AddAssign<&StackAddr> for StackAddr {
+    fn add_assign(&mut self, rhs: &StackAddr) {
+        self.segments.extend_from_slice(&rhs.segments);
+    }
+}
+
+impl From<SocketAddr> for StackAddr {
+    /// Converts into a TCP address, e.g. `/ip4/127.0.0.1/tcp/8080`.
+    ///
+    /// A non-zero IPv6 scope id is kept as a numeric zone:
+    /// `/ip6zone/2/ip6/fe80::1/tcp/8080`.
+    fn from(addr: SocketAddr) -> Self {
+        StackAddr::from_socket_addr(addr, TransportKind::Tcp)
+    }
+}
+
+impl From<(SocketAddr, TransportKind)> for StackAddr {
+    /// Converts with [`StackAddr::from_socket_addr`].
+    fn from((addr, kind): (SocketAddr, TransportKind)) -> Self {
+        StackAddr::from_socket_addr(addr, kind)
+    }
+}
+
+impl From<IpAddr> for StackAddr {
+    fn from(ip: IpAddr) -> Self {
+        StackAddr::empty().with_ip(ip)
+    }
+}
+
+impl TryFrom<&StackAddr> for SocketAddr {
+    type Error = StackAddrError;
+
+    /// Converts an address with an IP and a port, applying a numeric `/ip6zone`.
+    ///
+    /// Unlike [`StackAddr::socket_addrs`], this never looks anything up: addresses
+    /// naming a host or an interface zone are rejected with [`StackAddrError::NoEquivalent`].
+    fn try_from(addr: &StackAddr) -> Result<Self, Self::Error> {
+        if addr.is_empty() {
+            return Err(StackAddrError::EmptyAddress);
         }
+        let ip = match addr.ip() {
+            Some(ip) => ip,
+            None => {
+                return Err(match addr.name() {
+                    Some(name) => StackAddrError::NoEquivalent(format!("unresolved name {}", name)),
+                    None => StackAddrError::MissingPart("ip address"),
+                });
+            }
+        };
+        let port = addr
+            .port()
+            .ok_or(StackAddrError::MissingPart("transport port"))?;
+        let scope_id = addr.numeric_scope_id()?;
+        Ok(scoped_socket_addr(ip, port, scope_id))
+    }
+}
 
-        Ok(StackAddr { segments })
+impl TryFrom<&StackAddr> for SocketAddrV4 {
+    type Error = StackAddrError;
+
+    fn try_from(addr: &StackAddr) -> Result<Self, Self::Error> {
+        match SocketAddr::try_from(addr)? {
+            SocketAddr::V4(v4) => Ok(v4),
+            SocketAddr::V6(v6) => Err(StackAddrError::NoEquivalent(format!(
+                "{} as an IPv4 socket address",
+                v6.ip()
+            ))),
+        }
+    }
+}
+
+impl TryFrom<&StackAddr> for SocketAddrV6 {
+    type Error = StackAddrError;
+
+    fn try_from(addr: &StackAddr) -> Result<Self, Self::Error> {
+        match SocketAddr::try_from(addr)? {
+            SocketAddr::V6(v6) => Ok(v6),
+            SocketAddr::V4(v4) => Err(StackAddrError::NoEquivalent(format!(
+                "{} as an IPv6 socket address",
+                v4.ip()
+            ))),
+        }
+    }
+}
+
+#[cfg(unix)]
+impl TryFrom<&StackAddr> for std::os::unix::net::SocketAddr {
+    type Error = StackAddrError;
+
+    /// Converts the `/unix` path of the address.
+    ///
+    /// A path starting with a NUL byte becomes an abstract-namespace address, which
+    /// only Linux and Android support.
+    fn try_from(addr: &StackAddr) -> Result<Self, Self::Error> {
+        use std::os::unix::{ffi::OsStrExt, net::SocketAddr as UnixSocketAddr};
+
+        let path = addr
+            .unix_path()
+            .ok_or(StackAddrError::MissingPart("unix socket path"))?;
+        let invalid = |e: io::Error| StackAddrError::InvalidValue {
+            protocol: "unix".to_string(),
+            raw: path.display().to_string(),
+            reason: e.to_string(),
+        };
+        match path.as_os_str().as_bytes().strip_prefix(b"\0") {
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            Some(name) => {
+                #[cfg(target_os = "android")]
+                use std::os::android::net::SocketAddrExt;
+                #[cfg(target_os = "linux")]
+                use std::os::linux::net::SocketAddrExt;
+
+                UnixSocketAddr::from_abstract_name(name).map_err(invalid)
+            }
+            #[cfg(not(any(target_os = "linux", target_os = "android")))]
+            Some(_) => Err(StackAddrError::NoEquivalent(
+                "abstract unix socket on this platform".to_string(),
+            )),
+            None => UnixSocketAddr::from_pathname(path).map_err(invalid),
+        }
+    }
+}
+
+#[cfg(unix)]
+impl TryFrom<&std::os::unix::net::SocketAddr> for StackAddr {
+    type Error = StackAddrError;
+
+    /// Converts a named or abstract Unix socket address into `/unix/<path>`, keeping
+    /// the raw path bytes.
+    ///
+    /// Unnamed addresses, such as those of unbound or paired sockets, are rejected.
+    fn try_from(addr: &std::os::unix::net::SocketAddr) -> Result<Self, Self::Error> {
+        if let Some(path) = addr.as_pathname() {
+            return Ok(StackAddr::empty().with_unix_path(path));
+        }
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        {
+            #[cfg(target_os = "android")]
+            use std::os::android::net::SocketAddrExt;
+            #[cfg(target_os = "linux")]
+            use std::os::linux::net::SocketAddrExt;
+            use std::{ffi::OsString, os::unix::ffi::OsStringExt};
+
+            if let Some(name) = addr.as_abstract_name() {
+                let path = [&b"\0"[..], name].concat();
+                return Ok(StackAddr::empty().with_unix_path(OsString::from_vec(path)));
+            }
+        }
+        Err(StackAddrError::NoEquivalent(
+            "unnamed unix socket".to_string(),
+        ))
+    }
+}
+
+impl TryFrom<&[u8]> for StackAddr {
+    type Error = StackAddrError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        StackAddr::from_bytes(bytes)
     }
 }
 
@@ -574,19 +1101,23 @@ impl ToSocketAddrs for StackAddr {
     type Iter = std::vec::IntoIter<SocketAddr>;
 
     fn to_socket_addrs(&self) -> io::Result<Self::Iter> {
-        let (host, port) = self
-            .host_port()
-            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?;
-        (host.as_str(), port)
-            .to_socket_addrs()
-            .map(|iter| iter.collect::<Vec<_>>().into_iter())
+        self.socket_addrs()
+            .map(|addrs| addrs.into_iter())
+            .map_err(|e| match e {
+                StackAddrError::ResolutionFailed(_) => io::Error::other(e),
+                _ => io::Error::new(io::ErrorKind::InvalidInput, e),
+            })
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::segment::{Segment, identity::Identity, protocol::Protocol};
+    use crate::segment::{
+        Segment,
+        identity::{Identity, KeyType, Multibase},
+        protocol::Protocol,
+    };
     use bytes::Bytes;
     use std::net::{IpAddr, Ipv6Addr};
 
@@ -639,25 +1170,145 @@ mod tests {
             addr.segments().last(),
             Some(Segment::Identity(Identity::Uuid(_)))
         ));
+
+        for form in [
+            "550e8400e29b41d4a716446655440000",
+            "550E8400-E29B-41D4-A716-446655440000",
+            "{550e8400-e29b-41d4-a716-446655440000}",
+            "urn:uuid:550e8400-e29b-41d4-a716-446655440000",
+        ] {
+            let other: StackAddr = format!("/uuid/{}", form).parse().unwrap();
+            assert_eq!(other, addr);
+        }
+        assert!(
+            "/uuid/550e8400-e29b-41d4-a716"
+                .parse::<StackAddr>()
+                .is_err()
+        );
     }
 
     #[test]
-    fn test_identity_custom() {
+    fn test_identity_multibase_input() {
         let id = random_bytes32();
-        let encoded = base32::encode(base32::Alphabet::Rfc4648 { padding: false }, &id);
-        let s = format!("/identity/myproto/{}", encoded);
+        let identity = Identity::PeerId(id.clone());
+        let canonical: StackAddr = format!("/ip4/127.0.0.1/udp/4433/quic{}", identity)
+            .parse()
+            .unwrap();
+
+        for base in [
+            Multibase::Base58Btc,
+            Multibase::Base32,
+            Multibase::Base64Url,
+        ] {
+            let s = format!(
+                "/ip4/127.0.0.1/udp/4433/quic/peer/{}",
+                identity.encode(base)
+            );
+            let addr: StackAddr = s.parse().unwrap();
+            assert_eq!(addr, canonical);
+            // Display always uses the canonical base32 form.
+            assert_eq!(addr.to_string(), canonical.to_string());
+        }
+    }
+
+    #[test]
+    fn test_identity_typed_key() {
+        // The Ed25519 base point, a valid public key.
+        let mut key = [0x66; 32];
+        key[0] = 0x58;
+        let identity = Identity::Ed25519(Bytes::copy_from_slice(&key));
+        assert_eq!(identity.key_type(), Some(KeyType::Ed25519));
+
+        let s = format!("/ip4/127.0.0.1/udp/4433/quic{}", identity);
+        assert!(s.ends_with(&format!("/ed25519/{}", identity.to_base32())));
         let addr: StackAddr = s.parse().unwrap();
+        assert_eq!(addr.get_identity(), Some(&identity));
+        assert_eq!(StackAddr::from_bytes(&addr.to_bytes()).unwrap(), addr);
+
+        let truncated = format!(
+            "/ed25519/{}",
+            base32::encode(base32::Alphabet::Rfc4648 { padding: false }, &key[..31])
+        );
+        let err = truncated.parse::<StackAddr>().unwrap_err();
         assert!(matches!(
-            addr.segments().last(),
-            Some(Segment::Identity(Identity::Custom { .. }))
+            err.kind(),
+            StackAddrError::InvalidValue { protocol, reason, .. }
+                if protocol == "ed25519" && reason == "expected 32 bytes"
         ));
+
+        let short = StackAddr::empty().with_identity(Identity::P256(Bytes::from_static(&[2; 32])));
+        assert!(StackAddr::from_bytes(&short.to_bytes()).is_err());
     }
 
     #[test]
-    fn test_metadata_segment() {
-        let addr: StackAddr = "/meta/env/production".parse().unwrap();
+    fn test_identity_accessors() {
+        let relay = random_bytes32();
+        let dest = random_bytes32();
+        let uuid = uuid::Uuid::new_v4();
+        let addr = StackAddr::from_str("/ip4/192.0.2.1/udp/4433/quic")
+            .unwrap()
+            .with_identity(Identity::NodeId(relay.clone()))
+            .with_protocol(Protocol::Custom("relay".into()))
+            .with_identity(Identity::PeerId(dest.clone()))
+            .with_identity(Identity::Uuid(uuid));
+
+        assert_eq!(addr.identities().len(), 3);
+        assert_eq!(addr.get_identity(), Some(&Identity::NodeId(relay.clone())));
+        assert_eq!(addr.last_identity(), Some(&Identity::Uuid(uuid)));
+        assert_eq!(addr.node_id(), Some(&relay));
+        assert_eq!(addr.peer_id(), Some(&dest));
+        assert_eq!(addr.uuid(), Some(uuid));
+
+        let stripped = addr.without_identities();
+        assert!(stripped.identities().is_empty());
         assert_eq!(
-            addr.segments(),
+            stripped.to_string(),
+            "/ip4/192.0.2.1/udp/4433/quic/custom/relay"
+        );
+
+        let other = random_bytes32();
+        let rewritten = addr.with_identity_replaced(Identity::PeerId(other.clone()));
+        assert_eq!(rewritten.identities().len(), 3);
+        assert_eq!(
+            rewritten.last_identity(),
+            Some(&Identity::PeerId(other.clone()))
+        );
+        assert_eq!(rewritten.uuid(), None);
+
+        let appended = stripped.with_identity_replaced(Identity::PeerId(other.clone()));
+        assert_eq!(appended.identities(), vec![&Identity::PeerId(other)]);
+    }
+
+    #[test]
+    fn test_same_identity() {
+        let id = random_bytes32();
+        let a = StackAddr::from_str("/ip4/127.0.0.1/udp/4433/quic")
+            .unwrap()
+            .with_identity(Identity::NodeId(id.clone()));
+        let b = StackAddr::from_str("/dns/example.com/tcp/443/tls")
+            .unwrap()
+            .with_identity(Identity::PeerId(id));
+        assert!(a.same_identity(&b));
+        assert!(!a.same_identity(&StackAddr::from_str("/ip4/127.0.0.1/udp/4433/quic").unwrap()));
+    }
+
+    #[test]
+    fn test_identity_custom() {
+        let id = random_bytes32();
+        let encoded = base32::encode(base32::Alphabet::Rfc4648 { padding: false }, &id);
+        let s = format!("/identity/myproto/{}", encoded);
+        let addr: StackAddr = s.parse().unwrap();
+        assert!(matches!(
+            addr.segments().last(),
+            Some(Segment::Identity(Identity::Custom { .. }))
+        ));
+    }
+
+    #[test]
+    fn test_metadata_segment() {
+        let addr: StackAddr = "/meta/env/production".parse().unwrap();
+        assert_eq!(
+            addr.segments(),
             &[Segment::Metadata("env".into(), "production".into())]
         );
     }
@@ -674,6 +1325,659 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_escaped_values_roundtrip() {
+        let addr = StackAddr::empty()
+            .with_dns_name("example.com")
+            .with_protocol(Protocol::Tcp(443))
+            .with_path("dir/file")
+            .with_path("")
+            .with_path("tcp")
+            .with_meta("route", "a/b")
+            .with_meta("", "100%");
+        let s = addr.to_string();
+        assert_eq!(
+            s,
+            "/dns/example.com/tcp/443/dir%2Ffile/%/%74cp/meta/route/a%2Fb/meta/%/100%25"
+        );
+        assert_eq!(s.parse::<StackAddr>().unwrap(), addr);
+    }
+
+    #[test]
+    fn test_invalid_percent_escape() {
+        assert!(matches!(
+            "/meta/key/bad%zz".parse::<StackAddr>().unwrap_err().kind(),
+            StackAddrError::InvalidEncoding(_)
+        ));
+    }
+
+    #[test]
+    fn test_display_parse_roundtrip_arbitrary() {
+        use rand::Rng;
+        use rand::seq::IndexedRandom;
+
+        const PIECES: &[&str] = &[
+            "", "/", "%", "%2F", "a", "Z", "-", ".", " ", "tcp", "meta", "é", "日本", "?", "#",
+        ];
+
+        fn arbitrary_string(rng: &mut impl Rng) -> String {
+            let len = rng.random_range(0..4);
+            (0..len)
+                .map(|_| *PIECES.choose(rng).unwrap())
+                .collect::<String>()
+        }
+
+        let mut rng = rand::rng();
+        for _ in 0..1000 {
+            let mut addr = StackAddr::empty();
+            for _ in 0..rng.random_range(0..6) {
+                let seg = match rng.random_range(0..9) {
+                    0 => Segment::Path(arbitrary_string(&mut rng)),
+                    1 => Segment::Metadata(arbitrary_string(&mut rng), arbitrary_string(&mut rng)),
+                    2 => Segment::Protocol(Protocol::Dns(arbitrary_string(&mut rng))),
+                    3 => Segment::Protocol(Protocol::Dns4(arbitrary_string(&mut rng))),
+                    4 => Segment::Protocol(Protocol::Dns6(arbitrary_string(&mut rng))),
+                    5 => Segment::Protocol(Protocol::Custom(arbitrary_string(&mut rng))),
+                    6 => Segment::Protocol(Protocol::Onion(arbitrary_string(&mut rng))),
+                    7 => Segment::Identity(Identity::Custom {
+                        kind: arbitrary_string(&mut rng),
+                        id: random_bytes32(),
+                    }),
+                    _ => Segment::Protocol(Protocol::Tcp(rng.random())),
+                };
+                addr.push(seg);
+            }
+            let s = addr.to_string();
+            let parsed: StackAddr = s
+                .parse()
+                .unwrap_or_else(|e| panic!("failed to parse {:?}: {}", s, e));
+            assert_eq!(parsed, addr, "round-trip mismatch for {:?}", s);
+        }
+    }
+
+    fn test_registry() -> crate::ProtocolRegistry {
+        use crate::registry::ProtocolSpec;
+
+        let mut registry = crate::ProtocolRegistry::new();
+        registry
+            .register(
+                ProtocolSpec::new("rudp", 0x1001)
+                    .with_arity(1)
+                    .with_validator(|v| {
+                        v.parse::<u16>()
+                            .map(|_| ())
+                            .map_err(|_| StackAddrError::InvalidEncoding("rudp port"))
+                    }),
+            )
+            .unwrap();
+        registry
+            .register(ProtocolSpec::new("shm", 0x1002).with_arity(2))
+            .unwrap();
+        registry
+            .register(ProtocolSpec::new("noise", 0x1003))
+            .unwrap();
+        registry
+    }
+
+    #[test]
+    fn test_parse_with_registry() {
+        let registry = test_registry();
+        let s = "/ip4/10.0.0.1/rudp/9000/noise/shm/ring%2F0/4096/images";
+        let addr = StackAddr::parse_with(&registry, s).unwrap();
+        assert_eq!(
+            addr.segments(),
+            &[
+                Segment::Protocol(Protocol::Ip4("10.0.0.1".parse().unwrap())),
+                Segment::Protocol(Protocol::Registered {
+                    name: "rudp".into(),
+                    values: vec!["9000".into()],
+                }),
+                Segment::Protocol(Protocol::Registered {
+                    name: "noise".into(),
+                    values: vec![],
+                }),
+                Segment::Protocol(Protocol::Registered {
+                    name: "shm".into(),
+                    values: vec!["ring/0".into(), "4096".into()],
+                }),
+                Segment::Path("images".into()),
+            ]
+        );
+        assert_eq!(addr.to_string(), s);
+        assert_eq!(
+            StackAddr::parse_with(&registry, &addr.to_string()).unwrap(),
+            addr
+        );
+
+        // Without the registry the same words fall back to paths.
+        let plain: StackAddr = "/ip4/10.0.0.1/rudp/9000".parse().unwrap();
+        assert_eq!(plain.segments()[1], Segment::Path("rudp".into()));
+    }
+
+    #[test]
+    fn test_registry_name_as_path() {
+        let registry = test_registry();
+        let addr = StackAddr::empty()
+            .with_ipv4("10.0.0.1".parse().unwrap())
+            .with_path("noise")
+            .with_path("rudp");
+        let s = addr.to_string_with(&registry);
+        assert_eq!(s, "/ip4/10.0.0.1/%6Eoise/%72udp");
+        assert_eq!(StackAddr::parse_with(&registry, &s).unwrap(), addr);
+    }
+
+    #[test]
+    fn test_parse_with_registry_errors() {
+        let registry = test_registry();
+        assert!(matches!(
+            StackAddr::parse_with(&registry, "/ip4/10.0.0.1/rudp/not-a-port")
+                .unwrap_err()
+                .kind(),
+            StackAddrError::InvalidEncoding("rudp port")
+        ));
+        assert!(matches!(
+            StackAddr::parse_with(&registry, "/shm/ring-0")
+                .unwrap_err()
+                .kind(),
+            StackAddrError::MissingPart(_)
+        ));
+    }
+
+    #[test]
+    fn test_parse_with_options_strict() {
+        let strict = ParseOptions::new().with_strict(true);
+
+        let err = StackAddr::parse_with_options("/ip4/1.2.3.4/tpc/80", &strict).unwrap_err();
+        assert!(matches!(
+            err,
+            StackAddrError::ParseError {
+                segment_index: 1,
+                protocol: None,
+                ..
+            }
+        ));
+        assert_eq!(
+            err.kind(),
+            &StackAddrError::UnknownProtocol("tpc".to_string())
+        );
+
+        let addr =
+            StackAddr::parse_with_options("/ip4/1.2.3.4/tcp/80/path/a%2Fb/path/tcp", &strict)
+                .unwrap();
+        assert_eq!(
+            addr,
+            "/ip4/1.2.3.4/tcp/80/a%2Fb/%74cp"
+                .parse::<StackAddr>()
+                .unwrap()
+        );
+
+        // Registered protocols are still recognised in strict mode.
+        let registry = test_registry();
+        let opts = strict.with_registry(&registry);
+        assert!(StackAddr::parse_with_options("/ip4/10.0.0.1/rudp/9000", &opts).is_ok());
+
+        // Lenient mode keeps treating unknown words, including `path`, as paths.
+        let addr = StackAddr::parse_with_options("/path/x", &ParseOptions::new()).unwrap();
+        assert_eq!(addr.segments().len(), 2);
+        let addr: StackAddr = "/dns/x/tcp/80/http/path/to/file".parse().unwrap();
+        assert_eq!(
+            &addr.segments()[3..],
+            &[
+                Segment::Path("path".into()),
+                Segment::Path("to".into()),
+                Segment::Path("file".into())
+            ]
+        );
+        assert_eq!(addr.to_string(), "/dns/x/tcp/80/http/path/to/file");
+    }
+
+    #[test]
+    fn test_binary_registered() {
+        let registry = test_registry();
+        let addr = StackAddr::parse_with(&registry, "/ip4/10.0.0.1/rudp/9000/noise").unwrap();
+
+        // Self-describing form decodes without a registry.
+        let generic = addr.to_bytes();
+        assert_eq!(StackAddr::from_bytes(&generic).unwrap(), addr);
+
+        // Registered codes are more compact but need the registry to decode.
+        let compact = addr.to_bytes_with(&registry);
+        assert!(compact.len() < generic.len());
+        assert_eq!(
+            StackAddr::from_bytes_with(&registry, &compact).unwrap(),
+            addr
+        );
+        assert!(matches!(
+            StackAddr::from_bytes(&compact),
+            Err(StackAddrError::UnknownProtocol(_))
+        ));
+    }
+
+    #[test]
+    fn test_validate() {
+        let addr: StackAddr = "/ip4/127.0.0.1/udp/4433/quic".parse().unwrap();
+        assert!(addr.validate().is_ok());
+
+        let addr: StackAddr = "/tls/udp/53".parse().unwrap();
+        let violations = addr.validate().unwrap_err();
+        assert_eq!(violations[0], Violation::TlsWithoutTcp { index: 0 });
+    }
+
+    #[test]
+    fn test_parse_strict() {
+        assert!(StackAddr::parse_strict("/ip4/127.0.0.1/tcp/443/tls/http").is_ok());
+
+        let relay: StackAddr = "/ip4/10.0.0.1/udp/4433/quic/node/AEBAGBAF".parse().unwrap();
+        let inner: StackAddr = "/ip4/192.168.1.2/tcp/80/http".parse().unwrap();
+        let addr = relay.encapsulate(&inner);
+        assert_eq!(StackAddr::parse_strict(&addr.to_string()).unwrap(), addr);
+
+        let err = StackAddr::parse_strict("/tcp/80/ip4/1.2.3.4").unwrap_err();
+        assert!(matches!(err, StackAddrError::InvalidStack(ref v) if v.len() == 2));
+        assert_eq!(
+            err.to_string(),
+            "Invalid stack: segment 0: transport without a network layer; \
+             segment 1: lower layer after a higher layer"
+        );
+    }
+
+    #[test]
+    fn test_layer_sub_stack() {
+        let addr: StackAddr = "/mac/aa:bb:cc:dd:ee:ff/ip4/10.0.0.1/tcp/443/tls/http/api/v1"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            addr.layer(Layer::Link).to_string(),
+            "/mac/aa:bb:cc:dd:ee:ff"
+        );
+        assert_eq!(addr.layer(Layer::Network).to_string(), "/ip4/10.0.0.1");
+        assert_eq!(addr.layer(Layer::Transport).to_string(), "/tcp/443/tls");
+        assert_eq!(addr.layer(Layer::Application).to_string(), "/http/api/v1");
+
+        let addr: StackAddr = "/meta/env/prod/ip4/10.0.0.1/udp/4433/quic".parse().unwrap();
+        assert_eq!(
+            addr.layer(Layer::Network).to_string(),
+            "/meta/env/prod/ip4/10.0.0.1"
+        );
+        assert!(addr.layer(Layer::Application).is_empty());
+    }
+
+    #[test]
+    fn test_split_at_layer() {
+        let addr: StackAddr = "/mac/aa:bb:cc:dd:ee:ff/ip4/10.0.0.1/tcp/80/http/index.html"
+            .parse()
+            .unwrap();
+
+        let (link, rest) = addr.split_at_layer(Layer::Network);
+        assert_eq!(link.to_string(), "/mac/aa:bb:cc:dd:ee:ff");
+        assert_eq!(rest.to_string(), "/ip4/10.0.0.1/tcp/80/http/index.html");
+
+        let (lower, app) = addr.split_at_layer(Layer::Application);
+        assert_eq!(
+            lower.to_string(),
+            "/mac/aa:bb:cc:dd:ee:ff/ip4/10.0.0.1/tcp/80"
+        );
+        assert_eq!(app.to_string(), "/http/index.html");
+
+        // No application layer: everything stays below the split.
+        let addr: StackAddr = "/ip4/10.0.0.1/tcp/80".parse().unwrap();
+        let (lower, upper) = addr.split_at_layer(Layer::Application);
+        assert_eq!(lower, addr);
+        assert!(upper.is_empty());
+    }
+
+    #[test]
+    fn test_encapsulate() {
+        let relay: StackAddr = "/ip4/10.0.0.1/udp/4433/quic".parse().unwrap();
+        let inner: StackAddr = "/ip4/192.168.0.2/tcp/80/http".parse().unwrap();
+        let addr = relay.encapsulate(&inner);
+        assert_eq!(
+            addr.to_string(),
+            "/ip4/10.0.0.1/udp/4433/quic/ip4/192.168.0.2/tcp/80/http"
+        );
+        assert_eq!(addr, relay.clone() + &inner);
+        assert_eq!(addr, relay.clone() + inner.clone());
+
+        let mut sum = relay.clone();
+        sum += &inner;
+        assert_eq!(sum, addr);
+        let mut sum = relay;
+        sum += inner;
+        assert_eq!(sum, addr);
+    }
+
+    #[test]
+    fn test_decapsulate() {
+        let addr: StackAddr = "/ip4/10.0.0.1/tcp/1/ip4/192.168.0.2/tcp/80/http"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            addr.decapsulate(&Protocol::Tcp(0)).to_string(),
+            "/ip4/10.0.0.1/tcp/1/ip4/192.168.0.2"
+        );
+        assert_eq!(
+            addr.decapsulate(&Protocol::Ip4(Ipv4Addr::UNSPECIFIED))
+                .to_string(),
+            "/ip4/10.0.0.1/tcp/1"
+        );
+        assert_eq!(addr.decapsulate(&Protocol::Quic), addr);
+
+        let custom: StackAddr = "/ip4/10.0.0.1/custom/a/tcp/1/custom/b/http"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            custom
+                .decapsulate(&Protocol::Custom("a".into()))
+                .to_string(),
+            "/ip4/10.0.0.1"
+        );
+        let registry = test_registry();
+        let registered =
+            StackAddr::parse_with(&registry, "/ip4/10.0.0.1/shm/ring-0/4096/rudp/9000").unwrap();
+        let shm = Protocol::Registered {
+            name: "shm".into(),
+            values: vec![],
+        };
+        assert_eq!(registered.decapsulate(&shm).to_string(), "/ip4/10.0.0.1");
+        assert_eq!(
+            addr.decapsulate_code(crate::codec::HTTP).to_string(),
+            "/ip4/10.0.0.1/tcp/1/ip4/192.168.0.2/tcp/80"
+        );
+        assert_eq!(
+            addr.decapsulate_code(crate::codec::IP4).to_string(),
+            "/ip4/10.0.0.1/tcp/1"
+        );
+    }
+
+    fn test_resolver() -> crate::StaticResolver {
+        crate::StaticResolver::new()
+            .with("example.com", "93.184.216.34".parse().unwrap())
+            .with("example.com", "2606:2800:220:1::".parse().unwrap())
+            .with("v4only.example", "10.0.0.1".parse().unwrap())
+    }
+
+    #[test]
+    fn test_resolve_with_family() {
+        let resolver = test_resolver();
+
+        let addr: StackAddr = "/dns/example.com/tcp/443/tls/http".parse().unwrap();
+        let resolved: Vec<String> = addr
+            .resolve_with(&resolver)
+            .unwrap()
+            .iter()
+            .map(|a| a.to_string())
+            .collect();
+        assert_eq!(
+            resolved,
+            vec![
+                "/ip4/93.184.216.34/tcp/443/tls/http",
+                "/ip6/2606:2800:220:1::/tcp/443/tls/http",
+            ]
+        );
+
+        let addr: StackAddr = "/dns4/example.com/tcp/443".parse().unwrap();
+        let resolved = addr.resolve_with(&resolver).unwrap();
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(resolved[0].to_string(), "/ip4/93.184.216.34/tcp/443");
+
+        let addr: StackAddr = "/dns6/example.com/tcp/443".parse().unwrap();
+        let resolved = addr.resolve_with(&resolver).unwrap();
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(resolved[0].to_string(), "/ip6/2606:2800:220:1::/tcp/443");
+
+        let addr: StackAddr = "/dns6/v4only.example/tcp/443".parse().unwrap();
+        assert!(matches!(
+            addr.resolve_with(&resolver),
+            Err(StackAddrError::ResolutionFailed(_))
+        ));
+    }
+
+    #[test]
+    fn test_ip6_zone() {
+        let addr: StackAddr = "/ip6zone/3/ip6/fe80::1/udp/4433/quic".parse().unwrap();
+        assert_eq!(addr.zone(), Some("3"));
+        assert_eq!(addr.to_string(), "/ip6zone/3/ip6/fe80::1/udp/4433/quic");
+        assert_eq!(StackAddr::from_bytes(&addr.to_bytes()).unwrap(), addr);
+
+        let sock = addr.socket_addr().unwrap();
+        let SocketAddr::V6(v6) = sock else {
+            panic!("expected an IPv6 socket address");
+        };
+        assert_eq!(v6.scope_id(), 3);
+        assert_eq!(addr.socket_addrs().unwrap(), vec![sock]);
+
+        // Interface names go through the resolver.
+        let named: StackAddr = "/ip6zone/eth0/ip6/fe80::1/tcp/80".parse().unwrap();
+        let resolver = crate::StaticResolver::new().with_zone("eth0", 7);
+        let resolved = named.socket_addrs_with(&resolver).unwrap();
+        assert!(matches!(resolved[0], SocketAddr::V6(v6) if v6.scope_id() == 7));
+        assert_eq!(named.socket_addr(), None);
+        assert!(
+            "/ip6zone/eth1/ip6/fe80::1/tcp/80"
+                .parse::<StackAddr>()
+                .unwrap()
+                .socket_addrs_with(&resolver)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_from_socket_addr_scope() {
+        let sock: SocketAddr = "[fe80::1%5]:8080".parse().unwrap();
+        let addr = StackAddr::from(sock);
+        assert_eq!(addr.to_string(), "/ip6zone/5/ip6/fe80::1/tcp/8080");
+        assert_eq!(addr.socket_addr(), Some(sock));
+
+        let sock: SocketAddr = "127.0.0.1:8080".parse().unwrap();
+        assert_eq!(StackAddr::from(sock).to_string(), "/ip4/127.0.0.1/tcp/8080");
+        assert_eq!(StackAddr::from(sock).socket_addr(), Some(sock));
+    }
+
+    #[test]
+    fn test_socket_addr_conversions() {
+        let sock: SocketAddr = "192.0.2.1:4433".parse().unwrap();
+        for (kind, expected) in [
+            (TransportKind::Udp, "/ip4/192.0.2.1/udp/4433"),
+            (TransportKind::TlsTcp, "/ip4/192.0.2.1/tcp/4433/tls"),
+            (TransportKind::Quic, "/ip4/192.0.2.1/udp/4433/quic"),
+            (TransportKind::Wss, "/ip4/192.0.2.1/tcp/4433/wss/4433"),
+            (
+                TransportKind::WebTransport,
+                "/ip4/192.0.2.1/udp/4433/wtr/4433",
+            ),
+        ] {
+            let addr = StackAddr::from_socket_addr(sock, kind);
+            assert_eq!(addr.to_string(), expected);
+            assert_eq!(addr.transport().map(|t| t.kind()), Some(kind));
+            assert!(addr.validate().is_ok(), "{}", expected);
+            assert_eq!(SocketAddr::try_from(&addr).unwrap(), sock);
+        }
+
+        let addr = StackAddr::from((sock, TransportKind::Quic));
+        assert_eq!(addr.to_string(), "/ip4/192.0.2.1/udp/4433/quic");
+        assert_eq!(
+            SocketAddrV4::try_from(&addr).unwrap(),
+            "192.0.2.1:4433".parse().unwrap()
+        );
+        assert!(matches!(
+            SocketAddrV6::try_from(&addr),
+            Err(StackAddrError::NoEquivalent(_))
+        ));
+
+        let ip: IpAddr = "::1".parse().unwrap();
+        assert_eq!(StackAddr::from(ip).to_string(), "/ip6/::1");
+        assert_eq!(
+            SocketAddr::try_from(&StackAddr::from(ip)),
+            Err(StackAddrError::MissingPart("transport port"))
+        );
+        assert_eq!(
+            SocketAddr::try_from(&StackAddr::empty()),
+            Err(StackAddrError::EmptyAddress)
+        );
+        let named: StackAddr = "/dns/example.com/tcp/443".parse().unwrap();
+        assert!(matches!(
+            SocketAddr::try_from(&named),
+            Err(StackAddrError::NoEquivalent(_))
+        ));
+        let zoned: StackAddr = "/ip6zone/eth0/ip6/fe80::1/tcp/80".parse().unwrap();
+        assert!(matches!(
+            SocketAddr::try_from(&zoned),
+            Err(StackAddrError::NoEquivalent(_))
+        ));
+    }
+
+    #[test]
+    fn test_unix_path() {
+        let addr: StackAddr = "/unix/%2Frun%2Fagent.sock/http".parse().unwrap();
+        assert_eq!(addr.unix_path(), Some(Path::new("/run/agent.sock")));
+        assert_eq!(addr.to_string(), "/unix/%2Frun%2Fagent.sock/http");
+        assert_eq!(
+            StackAddr::empty()
+                .with_unix_path("/run/agent.sock")
+                .with_protocol(Protocol::Http),
+            addr
+        );
+        assert_eq!(StackAddr::from_bytes(&addr.to_bytes()).unwrap(), addr);
+        assert_eq!(StackAddr::empty().unix_path(), None);
+
+        let abstract_addr = StackAddr::empty().with_unix_path("\0agent");
+        assert_eq!(abstract_addr.to_string(), "/unix/%00agent");
+        assert_eq!(
+            "/unix/%00agent".parse::<StackAddr>().unwrap(),
+            abstract_addr
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unix_path_not_utf8() {
+        use std::os::unix::{ffi::OsStrExt, net::SocketAddr as UnixSocketAddr};
+
+        let path = Path::new(std::ffi::OsStr::from_bytes(b"/tmp/a\xffb.sock"));
+        let addr = StackAddr::empty().with_unix_path(path);
+        assert_eq!(addr.to_string(), "/unix/%2Ftmp%2Fa%FFb.sock");
+        assert_eq!(addr.to_string().parse::<StackAddr>().unwrap(), addr);
+        assert_eq!(StackAddr::from_bytes(&addr.to_bytes()).unwrap(), addr);
+        assert_eq!(
+            crate::StackAddrRef::parse(&addr.to_string())
+                .unwrap()
+                .to_owned(),
+            addr
+        );
+
+        let sock = UnixSocketAddr::try_from(&addr).unwrap();
+        assert_eq!(sock.as_pathname(), Some(path));
+        assert_eq!(StackAddr::try_from(&sock).unwrap(), addr);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unix_socket_addr_conversions() {
+        use std::os::unix::net::SocketAddr as UnixSocketAddr;
+
+        let addr: StackAddr = "/unix/%2Frun%2Fagent.sock/http".parse().unwrap();
+        let sock = UnixSocketAddr::try_from(&addr).unwrap();
+        assert_eq!(sock.as_pathname(), Some(Path::new("/run/agent.sock")));
+        assert_eq!(
+            StackAddr::try_from(&sock).unwrap().to_string(),
+            "/unix/%2Frun%2Fagent.sock"
+        );
+        assert_eq!(
+            UnixSocketAddr::try_from(&"/ip4/127.0.0.1/tcp/80".parse::<StackAddr>().unwrap())
+                .unwrap_err(),
+            StackAddrError::MissingPart("unix socket path")
+        );
+
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::linux::net::SocketAddrExt;
+
+            let addr: StackAddr = "/unix/%00agent".parse().unwrap();
+            let sock = UnixSocketAddr::try_from(&addr).unwrap();
+            assert_eq!(sock.as_abstract_name(), Some(&b"agent"[..]));
+            assert_eq!(StackAddr::try_from(&sock).unwrap(), addr);
+        }
+    }
+
+    #[test]
+    fn test_socket_addrs_with_family() {
+        let resolver = test_resolver();
+
+        let addr: StackAddr = "/dns4/example.com/tcp/443".parse().unwrap();
+        assert_eq!(
+            addr.socket_addrs_with(&resolver).unwrap(),
+            vec!["93.184.216.34:443".parse::<SocketAddr>().unwrap()]
+        );
+
+        let addr: StackAddr = "/dns6/example.com/tcp/443".parse().unwrap();
+        assert_eq!(
+            addr.socket_addrs_with(&resolver).unwrap(),
+            vec!["[2606:2800:220:1::]:443".parse::<SocketAddr>().unwrap()]
+        );
+
+        let addr: StackAddr = "/dns/example.com/tcp/443".parse().unwrap();
+        assert_eq!(addr.socket_addrs_with(&resolver).unwrap().len(), 2);
+
+        let addr: StackAddr = "/dns6/v4only.example/tcp/443".parse().unwrap();
+        assert!(matches!(
+            addr.socket_addrs_with(&resolver),
+            Err(StackAddrError::ResolutionFailed(_))
+        ));
+    }
+
+    #[test]
+    fn test_socket_addrs_dns4_localhost() {
+        let addr: StackAddr = "/dns4/localhost/tcp/80".parse().unwrap();
+        let resolved = addr.socket_addrs().expect("resolution failed");
+        assert!(!resolved.is_empty());
+        assert!(resolved.iter().all(|sock| sock.is_ipv4()));
+    }
+
+    #[test]
+    fn test_resolve_family_mismatch() {
+        let mut addr: StackAddr = "/dns4/example.com/tcp/443".parse().unwrap();
+        let err = addr.resolve("::1".parse().unwrap()).unwrap_err();
+        assert!(matches!(err, StackAddrError::FamilyMismatch { .. }));
+        assert_eq!(
+            err.to_string(),
+            "IP address ::1 does not match /dns4/example.com"
+        );
+        assert_eq!(addr.to_string(), "/dns4/example.com/tcp/443");
+
+        addr.resolve("10.0.0.1".parse().unwrap()).unwrap();
+        assert_eq!(addr.to_string(), "/ip4/10.0.0.1/tcp/443");
+
+        let mut addr: StackAddr = "/dns/example.com/tcp/443".parse().unwrap();
+        addr.resolve("::1".parse().unwrap()).unwrap();
+        assert_eq!(addr.to_string(), "/ip6/::1/tcp/443");
+    }
+
+    #[test]
+    fn test_resolve_with_no_dns() {
+        let addr: StackAddr = "/ip4/127.0.0.1/tcp/80".parse().unwrap();
+        assert_eq!(addr.resolve_with(&test_resolver()).unwrap(), vec![addr]);
+    }
+
+    #[test]
+    fn test_resolve_with_async() {
+        use std::future::Future;
+        use std::pin::pin;
+        use std::task::{Context, Poll, Waker};
+
+        let resolver = test_resolver();
+        let addr: StackAddr = "/dns4/example.com/udp/4433/quic".parse().unwrap();
+        let mut fut = pin!(addr.resolve_with_async(&resolver));
+        let mut cx = Context::from_waker(Waker::noop());
+        match fut.as_mut().poll(&mut cx) {
+            Poll::Ready(resolved) => {
+                let resolved = resolved.unwrap();
+                assert_eq!(resolved.len(), 1);
+                assert_eq!(resolved[0].to_string(), "/ip4/93.184.216.34/udp/4433/quic");
+            }
+            Poll::Pending => panic!("static resolver should be ready immediately"),
+        }
+    }
+
     #[test]
     fn test_l2_to_l4() {
         let s = "/mac/aa:bb:cc:dd:ee:ff/ip4/192.168.1.1/tcp/8080";
@@ -739,6 +2043,157 @@ mod tests {
         assert_eq!(err.to_string(), "Missing foo");
     }
 
+    #[test]
+    fn test_parse_error_position() {
+        let err = "/ip4/1.2.3/tcp/80".parse::<StackAddr>().unwrap_err();
+        match &err {
+            StackAddrError::ParseError {
+                segment_index,
+                byte_range,
+                protocol,
+                kind,
+                ..
+            } => {
+                assert_eq!(*segment_index, 0);
+                assert_eq!(*byte_range, 5..10);
+                assert_eq!(protocol.as_deref(), Some("ip4"));
+                assert!(matches!(**kind, StackAddrError::InvalidIp(_)));
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+        assert_eq!(
+            err.to_string(),
+            "Invalid IP address: invalid IPv4 address syntax at segment 0 (ip4)\n\
+             /ip4/1.2.3/tcp/80\n     ^^^^^"
+        );
+
+        // The caret is placed by chars within the line of the segment.
+        let err = "/é\nb/ip4/1.2.3".parse::<StackAddr>().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid IP address: invalid IPv4 address syntax at segment 1 (ip4)\n\
+             b/ip4/1.2.3\n      ^^^^^"
+        );
+        let err = "/é/ip4/1.2.3".parse::<StackAddr>().unwrap_err();
+        assert!(err.to_string().ends_with("\n/é/ip4/1.2.3\n       ^^^^^"));
+
+        // A missing value points at the protocol word.
+        let err = "/ip4/127.0.0.1/tcp".parse::<StackAddr>().unwrap_err();
+        assert!(matches!(
+            err,
+            StackAddrError::ParseError { segment_index: 1, ref byte_range, .. } if *byte_range == (15..18)
+        ));
+        assert!(matches!(
+            err.kind(),
+            StackAddrError::MissingPart("tcp port")
+        ));
+
+        // Paths have no protocol.
+        let err = "/ip4/127.0.0.1/bad%zz".parse::<StackAddr>().unwrap_err();
+        assert!(matches!(
+            err,
+            StackAddrError::ParseError {
+                segment_index: 1,
+                protocol: None,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_value_errors() {
+        let kind = |s: &str| s.parse::<StackAddr>().unwrap_err().kind().clone();
+
+        assert_eq!(
+            kind("/ip4/127.0.0.1/tcp/8o"),
+            StackAddrError::InvalidPort {
+                protocol: "tcp".into(),
+                raw: "8o".into()
+            }
+        );
+        assert_eq!(
+            kind("/ip4/127.0.0.1/udp/70000"),
+            StackAddrError::InvalidValue {
+                protocol: "udp".into(),
+                raw: "70000".into(),
+                reason: "port out of range (0-65535)".into()
+            }
+        );
+        assert!(matches!(
+            kind("/mac/aa:bb:cc"),
+            StackAddrError::InvalidValue { protocol, .. } if protocol == "mac"
+        ));
+        assert_eq!(
+            kind("/ip4/127.0.0.1/tcp/80/http\n"),
+            StackAddrError::UnexpectedTrailing("\n".into())
+        );
+        assert_eq!(
+            kind(" \t/ip4/127.0.0.1/tcp/80"),
+            StackAddrError::UnexpectedLeading(" \t".into())
+        );
+        // Input that is only whitespace has no address for it to lead.
+        assert_eq!(
+            kind("  \n"),
+            StackAddrError::UnexpectedTrailing("  \n".into())
+        );
+
+        assert_eq!(
+            StackAddr::parse_strict("/").unwrap_err(),
+            StackAddrError::EmptyAddress
+        );
+        assert_eq!(
+            StackAddr::empty().host_port().unwrap_err(),
+            StackAddrError::EmptyAddress
+        );
+    }
+
+    #[test]
+    fn test_binary_roundtrip_all_segments() {
+        let node = random_bytes32();
+        let peer = random_bytes32();
+        let s = format!(
+            "/mac/aa:bb:cc:dd:ee:ff/ip4/192.168.1.1/ip6/::1/dns/example.com/dns4/v4.example.com\
+             /dns6/v6.example.com/unix/%2Frun%2Fa%20b.sock/tcp/443/udp/4433/tls/quic/http/https\
+             /ws/8080/wss/8443/wtr/4433\
+             /webrtc/onion/abcdefghijklmnop:80/custom/myproto/node/{}/peer/{}\
+             /uuid/550e8400-e29b-41d4-a716-446655440000/identity/myproto/{}\
+             /meta/env/production/images",
+            base32::encode(base32::Alphabet::Rfc4648 { padding: false }, &node),
+            base32::encode(base32::Alphabet::Rfc4648 { padding: false }, &peer),
+            base32::encode(base32::Alphabet::Rfc4648 { padding: false }, &node),
+        );
+        let addr: StackAddr = s.parse().unwrap();
+        let bytes = addr.to_bytes();
+        let decoded = StackAddr::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded, addr);
+        assert_eq!(decoded.to_string(), addr.to_string());
+    }
+
+    #[test]
+    fn test_binary_is_compact() {
+        let addr: StackAddr = "/ip4/192.168.10.10/udp/4433/quic".parse().unwrap();
+        let bytes = addr.to_bytes();
+        // 1+4 (ip4) + 1+2 (udp) + 1 (quic)
+        assert_eq!(bytes.len(), 9);
+        assert!(bytes.len() * 3 < addr.to_string().len());
+    }
+
+    #[test]
+    fn test_binary_try_from() {
+        let addr: StackAddr = "/ip6/::1/tcp/8080/http".parse().unwrap();
+        let bytes = addr.to_bytes();
+        let decoded = StackAddr::try_from(&bytes[..]).unwrap();
+        assert_eq!(decoded, addr);
+        assert!(StackAddr::try_from(&bytes[..bytes.len() - 2]).is_err());
+    }
+
+    #[test]
+    fn test_binary_empty() {
+        let addr = StackAddr::empty();
+        assert!(addr.to_bytes().is_empty());
+        assert_eq!(StackAddr::from_bytes(&[]).unwrap(), addr);
+    }
+
     #[cfg(feature = "serde")]
     #[test]
     fn test_serde() {
@@ -0,0 +1,481 @@
+//! Borrowed address views
+//!
+//! [`StackAddrRef`] is a zero-copy view over the string or binary form of an address.
+//! It validates its input once and then decodes segments on demand, borrowing names,
+//! paths and metadata from the input instead of allocating a [`StackAddr`].
+//!
+//! Values are only copied when they have to be decoded: percent-escaped values, and
+//! base32 identities in the string form.
+//!
+//! ```rust
+//! use stackaddr::StackAddrRef;
+//!
+//! let addr = StackAddrRef::parse("/dns/example.com/tcp/443/tls/http").unwrap();
+//! assert_eq!(addr.name().as_deref(), Some("example.com"));
+//! assert_eq!(addr.port(), Some(443));
+//!
+//! let owned = addr.to_owned();
+//! assert_eq!(owned.to_string(), "/dns/example.com/tcp/443/tls/http");
+//! ```
+
+use bytes::Bytes;
+use mac_addr::MacAddr;
+use std::{
+    borrow::Cow,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    path::{Path, PathBuf},
+};
+use uuid::Uuid;
+
+use crate::{
+    addr::StackAddr,
+    codec,
+    error::StackAddrError,
+    parse::{ParseOptions, TextSegments},
+    segment::{
+        Segment,
+        identity::{Identity, KeyType},
+        protocol::{Protocol, TransportProtocol},
+    },
+};
+
+/// A borrowed [`Protocol`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ProtocolRef<'a> {
+    /// MAC address (layer 2)
+    Mac(MacAddr),
+    /// IPv4 address (layer 3)
+    Ip4(Ipv4Addr),
+    /// IPv6 address (layer 3)
+    Ip6(Ipv6Addr),
+    /// IPv6 zone (scope) of the following `/ip6`
+    Ip6Zone(Cow<'a, str>),
+    /// DNS (unspecified family)
+    Dns(Cow<'a, str>),
+    /// DNS (IPv4)
+    Dns4(Cow<'a, str>),
+    /// DNS (IPv6)
+    Dns6(Cow<'a, str>),
+    /// Unix domain socket path
+    Unix(Cow<'a, Path>),
+    /// TCP port (layer 4)
+    Tcp(u16),
+    /// UDP port (layer 4)
+    Udp(u16),
+    /// TLS (over TCP)
+    Tls,
+    /// QUIC (over UDP)
+    Quic,
+    /// HTTP protocol
+    Http,
+    /// HTTPS (alias for `/tls/http`)
+    Https,
+    /// WebSocket (with port)
+    Ws(u16),
+    /// Secure WebSocket (with port)
+    Wss(u16),
+    /// WebTransport (over QUIC or HTTP/3)
+    WebTransport(u16),
+    /// WebRTC
+    WebRTC,
+    /// Tor Onion address (v2 or v3)
+    Onion(Cow<'a, str>),
+    /// Arbitrary custom protocol
+    Custom(Cow<'a, str>),
+    /// A user-defined protocol described by a [`ProtocolRegistry`](crate::ProtocolRegistry)
+    Registered {
+        name: Cow<'a, str>,
+        values: Vec<Cow<'a, str>>,
+    },
+}
+
+impl ProtocolRef<'_> {
+    /// Convert into an owned [`Protocol`].
+    pub fn into_owned(self) -> Protocol {
+        match self {
+            ProtocolRef::Mac(addr) => Protocol::Mac(addr),
+            ProtocolRef::Ip4(addr) => Protocol::Ip4(addr),
+            ProtocolRef::Ip6(addr) => Protocol::Ip6(addr),
+            ProtocolRef::Ip6Zone(zone) => Protocol::Ip6Zone(zone.into_owned()),
+            ProtocolRef::Dns(name) => Protocol::Dns(name.into_owned()),
+            ProtocolRef::Dns4(name) => Protocol::Dns4(name.into_owned()),
+            ProtocolRef::Dns6(name) => Protocol::Dns6(name.into_owned()),
+            ProtocolRef::Unix(path) => Protocol::Unix(path.into_owned()),
+            ProtocolRef::Tcp(port) => Protocol::Tcp(port),
+            ProtocolRef::Udp(port) => Protocol::Udp(port),
+            ProtocolRef::Tls => Protocol::Tls,
+            ProtocolRef::Quic => Protocol::Quic,
+            ProtocolRef::Http => Protocol::Http,
+            ProtocolRef::Https => Protocol::Https,
+            ProtocolRef::Ws(port) => Protocol::Ws(port),
+            ProtocolRef::Wss(port) => Protocol::Wss(port),
+            ProtocolRef::WebTransport(port) => Protocol::WebTransport(port),
+            ProtocolRef::WebRTC => Protocol::WebRTC,
+            ProtocolRef::Onion(addr) => Protocol::Onion(addr.into_owned()),
+            ProtocolRef::Custom(name) => Protocol::Custom(name.into_owned()),
+            ProtocolRef::Registered { name, values } => Protocol::Registered {
+                name: name.into_owned(),
+                values: values.into_iter().map(Cow::into_owned).collect(),
+            },
+        }
+    }
+}
+
+/// Converts the raw bytes of a Unix socket path without copying borrowed input.
+///
+/// Any bytes are accepted on Unix; elsewhere paths must be UTF-8.
+pub(crate) fn unix_path(path: Cow<'_, [u8]>) -> Result<Cow<'_, Path>, StackAddrError> {
+    #[cfg(unix)]
+    {
+        use std::{
+            ffi::{OsStr, OsString},
+            os::unix::ffi::{OsStrExt, OsStringExt},
+        };
+        Ok(match path {
+            Cow::Borrowed(path) => Cow::Borrowed(Path::new(OsStr::from_bytes(path))),
+            Cow::Owned(path) => Cow::Owned(PathBuf::from(OsString::from_vec(path))),
+        })
+    }
+    #[cfg(not(unix))]
+    {
+        let invalid = StackAddrError::InvalidEncoding("utf-8 unix socket path");
+        Ok(match path {
+            Cow::Borrowed(path) => {
+                Cow::Borrowed(Path::new(std::str::from_utf8(path).map_err(|_| invalid)?))
+            }
+            Cow::Owned(path) => {
+                Cow::Owned(PathBuf::from(String::from_utf8(path).map_err(|_| invalid)?))
+            }
+        })
+    }
+}
+
+/// Returns the raw bytes of a Unix socket path, as written by the string and binary forms.
+pub(crate) fn unix_path_bytes(path: &Path) -> &[u8] {
+    path.as_os_str().as_encoded_bytes()
+}
+
+/// A borrowed [`Identity`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum IdentityRef<'a> {
+    /// A node ID, such as a public key (e.g. Ed25519)
+    NodeId(Cow<'a, [u8]>),
+    /// A peer ID, as used in many P2P protocols
+    PeerId(Cow<'a, [u8]>),
+    /// A UUID
+    Uuid(Uuid),
+    /// A custom identity with an explicit kind and ID bytes
+    Custom {
+        kind: Cow<'a, str>,
+        id: Cow<'a, [u8]>,
+    },
+    /// An Ed25519 public key
+    Ed25519(Cow<'a, [u8]>),
+    /// A secp256k1 public key
+    Secp256k1(Cow<'a, [u8]>),
+    /// A NIST P-256 public key
+    P256(Cow<'a, [u8]>),
+}
+
+impl<'a> IdentityRef<'a> {
+    /// Wraps a public key of the given type, which must already be checked.
+    pub(crate) fn key(key_type: KeyType, key: Cow<'a, [u8]>) -> Self {
+        match key_type {
+            KeyType::Ed25519 => IdentityRef::Ed25519(key),
+            KeyType::Secp256k1 => IdentityRef::Secp256k1(key),
+            KeyType::P256 => IdentityRef::P256(key),
+        }
+    }
+
+    /// Returns the underlying identity bytes for this variant.
+    ///
+    /// For `Uuid`, this returns the canonical 16-byte representation.
+    pub fn id_bytes(&self) -> &[u8] {
+        match self {
+            IdentityRef::NodeId(b) | IdentityRef::PeerId(b) => b,
+            IdentityRef::Custom { id, .. } => id,
+            IdentityRef::Uuid(u) => u.as_bytes(),
+            IdentityRef::Ed25519(k) | IdentityRef::Secp256k1(k) | IdentityRef::P256(k) => k,
+        }
+    }
+
+    /// Convert into an owned [`Identity`].
+    pub fn into_owned(self) -> Identity {
+        fn bytes(b: Cow<'_, [u8]>) -> Bytes {
+            match b {
+                Cow::Borrowed(b) => Bytes::copy_from_slice(b),
+                Cow::Owned(b) => Bytes::from(b),
+            }
+        }
+        match self {
+            IdentityRef::NodeId(b) => Identity::NodeId(bytes(b)),
+            IdentityRef::PeerId(b) => Identity::PeerId(bytes(b)),
+            IdentityRef::Uuid(u) => Identity::Uuid(u),
+            IdentityRef::Custom { kind, id } => Identity::Custom {
+                kind: kind.into_owned(),
+                id: bytes(id),
+            },
+            IdentityRef::Ed25519(k) => Identity::Ed25519(bytes(k)),
+            IdentityRef::Secp256k1(k) => Identity::Secp256k1(bytes(k)),
+            IdentityRef::P256(k) => Identity::P256(bytes(k)),
+        }
+    }
+}
+
+/// A borrowed [`Segment`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SegmentRef<'a> {
+    /// A transport or application protocol segment.
+    Protocol(ProtocolRef<'a>),
+    /// An identity segment (NodeId, PeerId, UUID, etc.).
+    Identity(IdentityRef<'a>),
+    /// A file or resource path segment.
+    Path(Cow<'a, str>),
+    /// A key-value metadata pair, expressed as `/meta/<key>/<value>`.
+    Metadata(Cow<'a, str>, Cow<'a, str>),
+}
+
+impl SegmentRef<'_> {
+    /// Convert into an owned [`Segment`].
+    pub fn into_owned(self) -> Segment {
+        match self {
+            SegmentRef::Protocol(p) => Segment::Protocol(p.into_owned()),
+            SegmentRef::Identity(i) => Segment::Identity(i.into_owned()),
+            SegmentRef::Path(p) => Segment::Path(p.into_owned()),
+            SegmentRef::Metadata(k, v) => Segment::Metadata(k.into_owned(), v.into_owned()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Source<'a> {
+    Text(&'a str),
+    Binary(&'a [u8]),
+}
+
+/// A zero-copy view over the string or binary form of a [`StackAddr`].
+///
+/// The input is validated when the view is created; the accessors then decode
+/// segments on demand without allocating.
+#[derive(Debug, Clone, Copy)]
+pub struct StackAddrRef<'a> {
+    source: Source<'a>,
+}
+
+impl<'a> StackAddrRef<'a> {
+    /// Create a view over the string form, e.g. `/ip4/127.0.0.1/tcp/443`.
+    ///
+    /// Accepts exactly what [`StackAddr`]'s `FromStr` accepts.
+    pub fn parse(s: &'a str) -> Result<Self, StackAddrError> {
+        for seg in TextSegments::new(s, ParseOptions::new()) {
+            seg?;
+        }
+        Ok(StackAddrRef {
+            source: Source::Text(s),
+        })
+    }
+
+    /// Create a view over the binary form produced by [`StackAddr::to_bytes`].
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<Self, StackAddrError> {
+        let mut buf = bytes;
+        while !buf.is_empty() {
+            codec::decode_segment_ref(&mut buf, None)?;
+        }
+        Ok(StackAddrRef {
+            source: Source::Binary(bytes),
+        })
+    }
+
+    /// Returns an iterator over the segments of the address.
+    pub fn segments(&self) -> SegmentRefs<'a> {
+        let inner = match self.source {
+            Source::Text(s) => Inner::Text(TextSegments::new(s, ParseOptions::new())),
+            Source::Binary(bytes) => Inner::Binary(bytes),
+        };
+        SegmentRefs { inner }
+    }
+
+    /// Convert into an owned [`StackAddr`].
+    pub fn to_owned(&self) -> StackAddr {
+        StackAddr::new(self.segments().map(SegmentRef::into_owned).collect())
+    }
+
+    /// Returns an iterator over the protocol segments of the address.
+    fn protocols(&self) -> impl Iterator<Item = ProtocolRef<'a>> + use<'a> {
+        self.segments().filter_map(|seg| match seg {
+            SegmentRef::Protocol(p) => Some(p),
+            _ => None,
+        })
+    }
+
+    /// Extract the transport protocol (if any) from the address.
+    pub fn transport(&self) -> Option<TransportProtocol> {
+        let mut port = None;
+        for p in self.protocols() {
+            match p {
+                ProtocolRef::Tcp(p) => port = Some(TransportProtocol::Tcp(p)),
+                ProtocolRef::Udp(p) => port = Some(TransportProtocol::Udp(p)),
+                ProtocolRef::Quic => {
+                    if let Some(TransportProtocol::Udp(p)) = port {
+                        return Some(TransportProtocol::Quic(p));
+                    }
+                }
+                ProtocolRef::Tls => {
+                    if let Some(TransportProtocol::Tcp(p)) = port {
+                        return Some(TransportProtocol::TlsTcp(p));
+                    }
+                }
+                ProtocolRef::Ws(p) => return Some(TransportProtocol::Ws(p)),
+                ProtocolRef::Wss(p) => return Some(TransportProtocol::Wss(p)),
+                ProtocolRef::WebTransport(p) => return Some(TransportProtocol::WebTransport(p)),
+                _ => continue,
+            }
+        }
+        port
+    }
+
+    /// Get the IP address from the stack address.
+    pub fn ip(&self) -> Option<IpAddr> {
+        self.protocols().find_map(|p| match p {
+            ProtocolRef::Ip4(addr) => Some(IpAddr::V4(addr)),
+            ProtocolRef::Ip6(addr) => Some(IpAddr::V6(addr)),
+            _ => None,
+        })
+    }
+
+    /// Get the port number from the stack address.
+    pub fn port(&self) -> Option<u16> {
+        self.protocols().find_map(|p| match p {
+            ProtocolRef::Tcp(p)
+            | ProtocolRef::Udp(p)
+            | ProtocolRef::Ws(p)
+            | ProtocolRef::Wss(p)
+            | ProtocolRef::WebTransport(p) => Some(p),
+            _ => None,
+        })
+    }
+
+    /// Get the DNS name from the stack address.
+    ///
+    /// The name is borrowed from the input unless it had to be unescaped.
+    pub fn name(&self) -> Option<Cow<'a, str>> {
+        self.protocols().find_map(|p| match p {
+            ProtocolRef::Dns(name) | ProtocolRef::Dns4(name) | ProtocolRef::Dns6(name) => {
+                Some(name)
+            }
+            _ => None,
+        })
+    }
+
+    /// Returns the first identity segment (NodeId, PeerId, UUID, or Custom) if present.
+    pub fn get_identity(&self) -> Option<IdentityRef<'a>> {
+        self.segments().find_map(|seg| match seg {
+            SegmentRef::Identity(id) => Some(id),
+            _ => None,
+        })
+    }
+
+    /// Check if the stack address is empty.
+    pub fn is_empty(&self) -> bool {
+        self.segments().next().is_none()
+    }
+}
+
+impl<'a> TryFrom<&'a str> for StackAddrRef<'a> {
+    type Error = StackAddrError;
+
+    fn try_from(s: &'a str) -> Result<Self, Self::Error> {
+        StackAddrRef::parse(s)
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for StackAddrRef<'a> {
+    type Error = StackAddrError;
+
+    fn try_from(bytes: &'a [u8]) -> Result<Self, Self::Error> {
+        StackAddrRef::from_bytes(bytes)
+    }
+}
+
+/// Iterator over the segments of a [`StackAddrRef`].
+#[derive(Debug, Clone)]
+pub struct SegmentRefs<'a> {
+    inner: Inner<'a>,
+}
+
+#[derive(Debug, Clone)]
+enum Inner<'a> {
+    Text(TextSegments<'a>),
+    Binary(&'a [u8]),
+}
+
+impl<'a> Iterator for SegmentRefs<'a> {
+    type Item = SegmentRef<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // The input was validated when the view was created, so decoding cannot fail here.
+        match &mut self.inner {
+            Inner::Text(segments) => segments.next()?.ok(),
+            Inner::Binary(buf) => {
+                if buf.is_empty() {
+                    return None;
+                }
+                codec::decode_segment_ref(buf, None).ok()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_accessors() {
+        let s = "/ip4/10.0.0.1/udp/4433/quic/node/AEBAGBAF/meta/env/prod";
+        let addr = StackAddrRef::parse(s).unwrap();
+        let owned: StackAddr = s.parse().unwrap();
+
+        assert_eq!(addr.ip(), owned.ip());
+        assert_eq!(addr.port(), owned.port());
+        assert_eq!(addr.transport(), owned.transport());
+        assert_eq!(addr.name(), None);
+        assert_eq!(
+            addr.get_identity().map(IdentityRef::into_owned).as_ref(),
+            owned.get_identity()
+        );
+        assert_eq!(addr.to_owned(), owned);
+        assert_eq!(addr.segments().count(), owned.segments().len());
+    }
+
+    #[test]
+    fn test_borrows_from_input() {
+        let addr = StackAddrRef::parse("/dns/example.com/tcp/443").unwrap();
+        assert!(matches!(addr.name(), Some(Cow::Borrowed("example.com"))));
+
+        let addr = StackAddrRef::parse("/dns/a%2Fb/tcp/443").unwrap();
+        assert!(matches!(addr.name(), Some(Cow::Owned(name)) if name == "a/b"));
+    }
+
+    #[test]
+    fn test_binary_view() {
+        let owned: StackAddr = "/dns6/example.com/tcp/443/tls/http/images/logo.png"
+            .parse()
+            .unwrap();
+        let bytes = owned.to_bytes();
+        let addr = StackAddrRef::from_bytes(&bytes).unwrap();
+
+        assert_eq!(addr.name().as_deref(), Some("example.com"));
+        assert!(matches!(addr.name(), Some(Cow::Borrowed(_))));
+        assert_eq!(addr.transport(), Some(TransportProtocol::TlsTcp(443)));
+        assert_eq!(addr.to_owned(), owned);
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        assert!(StackAddrRef::parse("/ip4/1.2.3/tcp/80").is_err());
+        assert!(StackAddrRef::parse("/tcp").is_err());
+        assert!(StackAddrRef::from_bytes(&[0x04, 1, 2]).is_err());
+        assert!(StackAddrRef::parse("").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,479 @@
+//! Binary codec
+//!
+//! Compact binary wire encoding for [`StackAddr`](crate::StackAddr).
+//!
+//! Each segment is encoded as an unsigned varint code followed by its value:
+//!
+//! - fixed-size values (IP addresses, MAC addresses, ports, UUIDs) are written as-is
+//! - variable-size values (names, identity bytes, paths, metadata) are prefixed
+//!   with their length as an unsigned varint
+//! - protocols without a value (e.g. `/tls`, `/quic`) are written as the code only
+//!
+//! Codes are stackaddr-specific and kept below `0x80` so that every built-in
+//! segment code fits in a single byte. Codes from [`FIRST_USER_CODE`](crate::codec::FIRST_USER_CODE) upwards are
+//! available to protocols registered in a [`ProtocolRegistry`].
+//!
+//! [`Protocol::Registered`] segments are written in a self-describing form
+//! (name followed by values) unless a registry is supplied, in which case the
+//! registered code is used instead.
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use mac_addr::MacAddr;
+use std::{
+    borrow::Cow,
+    net::{Ipv4Addr, Ipv6Addr},
+};
+use uuid::Uuid;
+
+use crate::{
+    borrowed::{IdentityRef, ProtocolRef, SegmentRef, unix_path, unix_path_bytes},
+    error::StackAddrError,
+    registry::ProtocolRegistry,
+    segment::{
+        Segment,
+        identity::{Identity, KeyType},
+        protocol::Protocol,
+    },
+};
+
+/// Code for [`Protocol::Mac`].
+pub const MAC: u64 = 0x01;
+/// Code for [`Protocol::Ip4`].
+pub const IP4: u64 = 0x04;
+/// Code for [`Protocol::Ip6`].
+pub const IP6: u64 = 0x06;
+/// Code for [`Protocol::Ip6Zone`].
+pub const IP6ZONE: u64 = 0x07;
+/// Code for [`Protocol::Dns`].
+pub const DNS: u64 = 0x10;
+/// Code for [`Protocol::Dns4`].
+pub const DNS4: u64 = 0x11;
+/// Code for [`Protocol::Dns6`].
+pub const DNS6: u64 = 0x12;
+/// Code for [`Protocol::Unix`].
+pub const UNIX: u64 = 0x13;
+/// Code for [`Protocol::Tcp`].
+pub const TCP: u64 = 0x20;
+/// Code for [`Protocol::Udp`].
+pub const UDP: u64 = 0x21;
+/// Code for [`Protocol::Tls`].
+pub const TLS: u64 = 0x30;
+/// Code for [`Protocol::Quic`].
+pub const QUIC: u64 = 0x31;
+/// Code for [`Protocol::Http`].
+pub const HTTP: u64 = 0x40;
+/// Code for [`Protocol::Https`].
+pub const HTTPS: u64 = 0x41;
+/// Code for [`Protocol::Ws`].
+pub const WS: u64 = 0x42;
+/// Code for [`Protocol::Wss`].
+pub const WSS: u64 = 0x43;
+/// Code for [`Protocol::WebTransport`].
+pub const WEBTRANSPORT: u64 = 0x44;
+/// Code for [`Protocol::WebRTC`].
+pub const WEBRTC: u64 = 0x45;
+/// Code for [`Protocol::Onion`].
+pub const ONION: u64 = 0x50;
+/// Code for [`Protocol::Custom`].
+pub const CUSTOM: u64 = 0x5f;
+/// Code for [`Identity::NodeId`].
+pub const NODE: u64 = 0x60;
+/// Code for [`Identity::PeerId`].
+pub const PEER: u64 = 0x61;
+/// Code for [`Identity::Uuid`].
+pub const UUID: u64 = 0x62;
+/// Code for [`Identity::Ed25519`].
+pub const ED25519: u64 = 0x63;
+/// Code for [`Identity::Secp256k1`].
+pub const SECP256K1: u64 = 0x64;
+/// Code for [`Identity::P256`].
+pub const P256: u64 = 0x65;
+/// Code for [`Identity::Custom`].
+pub const IDENTITY: u64 = 0x6f;
+/// Code for [`Segment::Path`].
+pub const PATH: u64 = 0x70;
+/// Code for [`Segment::Metadata`].
+pub const META: u64 = 0x71;
+/// Code for a self-describing [`Protocol::Registered`] segment.
+pub const REGISTERED: u64 = 0x7f;
+/// First code available to user-defined protocols.
+pub const FIRST_USER_CODE: u64 = 0x80;
+/// Largest code that fits in an encoded varint (63 bits).
+pub const MAX_CODE: u64 = (1 << 63) - 1;
+
+/// Maximum number of bytes in an encoded varint (63-bit values).
+const MAX_VARINT_LEN: usize = 9;
+
+/// Appends `value` as an unsigned LEB128 varint.
+pub(crate) fn put_varint(buf: &mut BytesMut, mut value: u64) {
+    while value >= 0x80 {
+        buf.put_u8((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buf.put_u8(value as u8);
+}
+
+/// Reads an unsigned LEB128 varint, advancing the buffer.
+pub(crate) fn get_varint(buf: &mut &[u8]) -> Result<u64, StackAddrError> {
+    let mut value = 0u64;
+    for i in 0..MAX_VARINT_LEN {
+        if !buf.has_remaining() {
+            return Err(StackAddrError::InvalidEncoding("truncated varint"));
+        }
+        let byte = buf.get_u8();
+        value |= u64::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            // Reject non-minimal encodings so that every value has exactly one form.
+            if byte == 0 && i > 0 {
+                return Err(StackAddrError::InvalidEncoding("non-minimal varint"));
+            }
+            return Ok(value);
+        }
+    }
+    Err(StackAddrError::InvalidEncoding("varint overflow"))
+}
+
+pub(crate) fn put_len_prefixed(buf: &mut BytesMut, value: &[u8]) {
+    put_varint(buf, value.len() as u64);
+    buf.put_slice(value);
+}
+
+fn get_fixed<'a>(buf: &mut &'a [u8], len: usize) -> Result<&'a [u8], StackAddrError> {
+    if buf.remaining() < len {
+        return Err(StackAddrError::InvalidEncoding("truncated value"));
+    }
+    let (value, rest) = buf.split_at(len);
+    *buf = rest;
+    Ok(value)
+}
+
+pub(crate) fn get_len_prefixed<'a>(buf: &mut &'a [u8]) -> Result<&'a [u8], StackAddrError> {
+    let len = get_varint(buf)?;
+    let len = usize::try_from(len).map_err(|_| StackAddrError::InvalidEncoding("length"))?;
+    get_fixed(buf, len)
+}
+
+fn get_str<'a>(buf: &mut &'a [u8]) -> Result<Cow<'a, str>, StackAddrError> {
+    let raw = get_len_prefixed(buf)?;
+    std::str::from_utf8(raw)
+        .map(Cow::Borrowed)
+        .map_err(|_| StackAddrError::InvalidEncoding("utf-8 string"))
+}
+
+fn get_port(buf: &mut &[u8]) -> Result<u16, StackAddrError> {
+    let raw = get_fixed(buf, 2)?;
+    Ok(u16::from_be_bytes([raw[0], raw[1]]))
+}
+
+/// Appends the binary form of a single segment.
+pub(crate) fn encode_segment(
+    buf: &mut BytesMut,
+    segment: &Segment,
+    registry: Option<&ProtocolRegistry>,
+) {
+    match segment {
+        Segment::Protocol(p) => match p {
+            Protocol::Mac(addr) => {
+                put_varint(buf, MAC);
+                buf.put_slice(&addr.octets());
+            }
+            Protocol::Ip4(addr) => {
+                put_varint(buf, IP4);
+                buf.put_slice(&addr.octets());
+            }
+            Protocol::Ip6(addr) => {
+                put_varint(buf, IP6);
+                buf.put_slice(&addr.octets());
+            }
+            Protocol::Ip6Zone(zone) => {
+                put_varint(buf, IP6ZONE);
+                put_len_prefixed(buf, zone.as_bytes());
+            }
+            Protocol::Dns(name) => {
+                put_varint(buf, DNS);
+                put_len_prefixed(buf, name.as_bytes());
+            }
+            Protocol::Dns4(name) => {
+                put_varint(buf, DNS4);
+                put_len_prefixed(buf, name.as_bytes());
+            }
+            Protocol::Dns6(name) => {
+                put_varint(buf, DNS6);
+                put_len_prefixed(buf, name.as_bytes());
+            }
+            Protocol::Unix(path) => {
+                put_varint(buf, UNIX);
+                put_len_prefixed(buf, unix_path_bytes(path));
+            }
+            Protocol::Tcp(port) => {
+                put_varint(buf, TCP);
+                buf.put_u16(*port);
+            }
+            Protocol::Udp(port) => {
+                put_varint(buf, UDP);
+                buf.put_u16(*port);
+            }
+            Protocol::Tls => put_varint(buf, TLS),
+            Protocol::Quic => put_varint(buf, QUIC),
+            Protocol::Http => put_varint(buf, HTTP),
+            Protocol::Https => put_varint(buf, HTTPS),
+            Protocol::Ws(port) => {
+                put_varint(buf, WS);
+                buf.put_u16(*port);
+            }
+            Protocol::Wss(port) => {
+                put_varint(buf, WSS);
+                buf.put_u16(*port);
+            }
+            Protocol::WebTransport(port) => {
+                put_varint(buf, WEBTRANSPORT);
+                buf.put_u16(*port);
+            }
+            Protocol::WebRTC => put_varint(buf, WEBRTC),
+            Protocol::Onion(addr) => {
+                put_varint(buf, ONION);
+                put_len_prefixed(buf, addr.as_bytes());
+            }
+            Protocol::Custom(name) => {
+                put_varint(buf, CUSTOM);
+                put_len_prefixed(buf, name.as_bytes());
+            }
+            Protocol::Registered { name, values } => {
+                match registry.and_then(|r| r.get(name)) {
+                    Some(spec) if spec.arity() == values.len() => put_varint(buf, spec.code()),
+                    _ => {
+                        put_varint(buf, REGISTERED);
+                        put_len_prefixed(buf, name.as_bytes());
+                        put_varint(buf, values.len() as u64);
+                    }
+                }
+                for value in values {
+                    put_len_prefixed(buf, value.as_bytes());
+                }
+            }
+        },
+        Segment::Identity(id) => match id {
+            Identity::NodeId(bytes) => {
+                put_varint(buf, NODE);
+                put_len_prefixed(buf, bytes);
+            }
+            Identity::PeerId(bytes) => {
+                put_varint(buf, PEER);
+                put_len_prefixed(buf, bytes);
+            }
+            Identity::Uuid(uuid) => {
+                put_varint(buf, UUID);
+                buf.put_slice(uuid.as_bytes());
+            }
+            Identity::Custom { kind, id } => {
+                put_varint(buf, IDENTITY);
+                put_len_prefixed(buf, kind.as_bytes());
+                put_len_prefixed(buf, id);
+            }
+            Identity::Ed25519(key) | Identity::Secp256k1(key) | Identity::P256(key) => {
+                let code = match id.key_type().expect("typed key") {
+                    KeyType::Ed25519 => ED25519,
+                    KeyType::Secp256k1 => SECP256K1,
+                    KeyType::P256 => P256,
+                };
+                put_varint(buf, code);
+                put_len_prefixed(buf, key);
+            }
+        },
+        Segment::Path(path) => {
+            put_varint(buf, PATH);
+            put_len_prefixed(buf, path.as_bytes());
+        }
+        Segment::Metadata(key, value) => {
+            put_varint(buf, META);
+            put_len_prefixed(buf, key.as_bytes());
+            put_len_prefixed(buf, value.as_bytes());
+        }
+    }
+}
+
+fn get_values<'a>(buf: &mut &'a [u8], count: u64) -> Result<Vec<Cow<'a, str>>, StackAddrError> {
+    // Every value takes at least one byte, which bounds the allocation below.
+    if count > buf.remaining() as u64 {
+        return Err(StackAddrError::InvalidEncoding("truncated value"));
+    }
+    (0..count).map(|_| get_str(buf)).collect()
+}
+
+/// Reads a single segment, advancing the buffer.
+///
+/// Variable-length values are borrowed from `buf`.
+pub(crate) fn decode_segment_ref<'a>(
+    buf: &mut &'a [u8],
+    registry: Option<&'a ProtocolRegistry>,
+) -> Result<SegmentRef<'a>, StackAddrError> {
+    let code = get_varint(buf)?;
+    let seg = match code {
+        MAC => {
+            let raw: [u8; 6] = get_fixed(buf, 6)?.try_into().expect("length checked");
+            SegmentRef::Protocol(ProtocolRef::Mac(MacAddr::from_octets(raw)))
+        }
+        IP4 => {
+            let raw: [u8; 4] = get_fixed(buf, 4)?.try_into().expect("length checked");
+            SegmentRef::Protocol(ProtocolRef::Ip4(Ipv4Addr::from(raw)))
+        }
+        IP6 => {
+            let raw: [u8; 16] = get_fixed(buf, 16)?.try_into().expect("length checked");
+            SegmentRef::Protocol(ProtocolRef::Ip6(Ipv6Addr::from(raw)))
+        }
+        IP6ZONE => SegmentRef::Protocol(ProtocolRef::Ip6Zone(get_str(buf)?)),
+        DNS => SegmentRef::Protocol(ProtocolRef::Dns(get_str(buf)?)),
+        DNS4 => SegmentRef::Protocol(ProtocolRef::Dns4(get_str(buf)?)),
+        DNS6 => SegmentRef::Protocol(ProtocolRef::Dns6(get_str(buf)?)),
+        UNIX => SegmentRef::Protocol(ProtocolRef::Unix(unix_path(Cow::Borrowed(
+            get_len_prefixed(buf)?,
+        ))?)),
+        TCP => SegmentRef::Protocol(ProtocolRef::Tcp(get_port(buf)?)),
+        UDP => SegmentRef::Protocol(ProtocolRef::Udp(get_port(buf)?)),
+        TLS => SegmentRef::Protocol(ProtocolRef::Tls),
+        QUIC => SegmentRef::Protocol(ProtocolRef::Quic),
+        HTTP => SegmentRef::Protocol(ProtocolRef::Http),
+        HTTPS => SegmentRef::Protocol(ProtocolRef::Https),
+        WS => SegmentRef::Protocol(ProtocolRef::Ws(get_port(buf)?)),
+        WSS => SegmentRef::Protocol(ProtocolRef::Wss(get_port(buf)?)),
+        WEBTRANSPORT => SegmentRef::Protocol(ProtocolRef::WebTransport(get_port(buf)?)),
+        WEBRTC => SegmentRef::Protocol(ProtocolRef::WebRTC),
+        ONION => SegmentRef::Protocol(ProtocolRef::Onion(get_str(buf)?)),
+        CUSTOM => SegmentRef::Protocol(ProtocolRef::Custom(get_str(buf)?)),
+        NODE => SegmentRef::Identity(IdentityRef::NodeId(Cow::Borrowed(get_len_prefixed(buf)?))),
+        PEER => SegmentRef::Identity(IdentityRef::PeerId(Cow::Borrowed(get_len_prefixed(buf)?))),
+        UUID => {
+            let raw = get_fixed(buf, 16)?;
+            let uuid =
+                Uuid::from_slice(raw).map_err(|_| StackAddrError::InvalidEncoding("uuid"))?;
+            SegmentRef::Identity(IdentityRef::Uuid(uuid))
+        }
+        IDENTITY => {
+            let kind = get_str(buf)?;
+            let id = Cow::Borrowed(get_len_prefixed(buf)?);
+            SegmentRef::Identity(IdentityRef::Custom { kind, id })
+        }
+        ED25519 | SECP256K1 | P256 => {
+            let key_type = match code {
+                ED25519 => KeyType::Ed25519,
+                SECP256K1 => KeyType::Secp256k1,
+                _ => KeyType::P256,
+            };
+            let key = get_len_prefixed(buf)?;
+            key_type
+                .check(key)
+                .map_err(|_| StackAddrError::InvalidEncoding("invalid public key"))?;
+            SegmentRef::Identity(IdentityRef::key(key_type, Cow::Borrowed(key)))
+        }
+        PATH => SegmentRef::Path(get_str(buf)?),
+        META => {
+            let key = get_str(buf)?;
+            let value = get_str(buf)?;
+            SegmentRef::Metadata(key, value)
+        }
+        REGISTERED => {
+            let name = get_str(buf)?;
+            let count = get_varint(buf)?;
+            let values = get_values(buf, count)?;
+            SegmentRef::Protocol(ProtocolRef::Registered { name, values })
+        }
+        other => match registry.and_then(|r| r.get_by_code(other)) {
+            Some(spec) => {
+                let values = get_values(buf, spec.arity() as u64)?;
+                for value in &values {
+                    spec.validate(value)?;
+                }
+                SegmentRef::Protocol(ProtocolRef::Registered {
+                    name: Cow::Borrowed(spec.name()),
+                    values,
+                })
+            }
+            None => {
+                return Err(StackAddrError::UnknownProtocol(format!(
+                    "code 0x{:x}",
+                    other
+                )));
+            }
+        },
+    };
+    Ok(seg)
+}
+
+/// Encodes a list of segments into a contiguous buffer.
+pub(crate) fn encode(segments: &[Segment], registry: Option<&ProtocolRegistry>) -> Bytes {
+    let mut buf = BytesMut::new();
+    for seg in segments {
+        encode_segment(&mut buf, seg, registry);
+    }
+    buf.freeze()
+}
+
+/// Decodes a buffer produced by [`encode`] back into segments.
+pub(crate) fn decode<'a>(
+    mut buf: &'a [u8],
+    registry: Option<&'a ProtocolRegistry>,
+) -> Result<Vec<Segment>, StackAddrError> {
+    let mut segments = Vec::new();
+    while buf.has_remaining() {
+        segments.push(decode_segment_ref(&mut buf, registry)?.into_owned());
+    }
+    Ok(segments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip_varint(value: u64) -> u64 {
+        let mut buf = BytesMut::new();
+        put_varint(&mut buf, value);
+        let mut slice = &buf[..];
+        let decoded = get_varint(&mut slice).unwrap();
+        assert!(slice.is_empty());
+        decoded
+    }
+
+    #[test]
+    fn test_varint_roundtrip() {
+        for value in [
+            0,
+            1,
+            0x7f,
+            0x80,
+            0x3fff,
+            0x4000,
+            u32::MAX as u64,
+            (1 << 63) - 1,
+        ] {
+            assert_eq!(roundtrip_varint(value), value);
+        }
+    }
+
+    #[test]
+    fn test_varint_single_byte_codes() {
+        let mut buf = BytesMut::new();
+        put_varint(&mut buf, META);
+        assert_eq!(&buf[..], &[0x71]);
+    }
+
+    #[test]
+    fn test_varint_rejects_invalid() {
+        assert!(get_varint(&mut &[0x80][..]).is_err());
+        assert!(get_varint(&mut &[0x80, 0x00][..]).is_err());
+        assert!(get_varint(&mut &[0xff; 10][..]).is_err());
+    }
+
+    #[test]
+    fn test_decode_unknown_code() {
+        let err = decode(&[0x7e], None).unwrap_err();
+        assert!(matches!(err, StackAddrError::UnknownProtocol(_)));
+    }
+
+    #[test]
+    fn test_decode_truncated_value() {
+        // ip4 code followed by only two address bytes
+        assert!(decode(&[0x04, 127, 0], None).is_err());
+        // dns code with a length longer than the remaining input
+        assert!(decode(&[0x10, 0x05, b'a'], None).is_err());
+    }
+}
@@ -0,0 +1,166 @@
+//! Public key identities (`crypto` feature)
+//!
+//! Checks that typed key identities ([`Identity::Ed25519`], [`Identity::Secp256k1`] and
+//! [`Identity::P256`]) are valid curve points, converts from the public key types of
+//! `ed25519-dalek`, `k256` and `p256`, and verifies signatures with [`Identity::verify`].
+//!
+//! ```rust
+//! use ed25519_dalek::{Signer, SigningKey};
+//! use stackaddr::{Identity, StackAddr};
+//!
+//! let key = SigningKey::from_bytes(&[7; 32]);
+//! let addr: StackAddr = "/ip4/127.0.0.1/tcp/4001".parse().unwrap();
+//! let addr = addr.with_identity(Identity::from(key.verifying_key()));
+//!
+//! let sig = key.sign(b"hello");
+//! let identity = addr.get_identity().unwrap();
+//! assert!(identity.verify(b"hello", &sig.to_bytes()).is_ok());
+//! assert!(identity.verify(b"goodbye", &sig.to_bytes()).is_err());
+//! ```
+
+use bytes::Bytes;
+use ed25519_dalek::Verifier;
+
+use crate::{
+    error::StackAddrError,
+    segment::identity::{Identity, KeyType},
+};
+
+/// Check that `key` decodes to a point on the curve of `key_type`.
+pub(crate) fn check_point(key_type: KeyType, key: &[u8]) -> Result<(), &'static str> {
+    let valid = match key_type {
+        KeyType::Ed25519 => key
+            .try_into()
+            .ok()
+            .is_some_and(|raw| ed25519_dalek::VerifyingKey::from_bytes(raw).is_ok()),
+        KeyType::Secp256k1 => k256::ecdsa::VerifyingKey::from_sec1_bytes(key).is_ok(),
+        KeyType::P256 => p256::ecdsa::VerifyingKey::from_sec1_bytes(key).is_ok(),
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err("not a valid curve point")
+    }
+}
+
+fn invalid(reason: impl ToString) -> StackAddrError {
+    StackAddrError::SignatureInvalid(reason.to_string())
+}
+
+impl Identity {
+    /// Verify `sig` over `msg` with the public key of this identity.
+    ///
+    /// Ed25519 expects a 64-byte signature. Secp256k1 and P-256 expect an ECDSA
+    /// signature over the SHA-256 digest of `msg`, either as 64 bytes (`r || s`)
+    /// or DER encoded.
+    ///
+    /// Identities that are not typed public keys cannot verify anything and
+    /// always return [`StackAddrError::SignatureInvalid`].
+    pub fn verify(&self, msg: &[u8], sig: &[u8]) -> Result<(), StackAddrError> {
+        match self {
+            Identity::Ed25519(key) => {
+                let raw: &[u8; 32] = key[..].try_into().map_err(|_| invalid("bad key"))?;
+                let key = ed25519_dalek::VerifyingKey::from_bytes(raw).map_err(invalid)?;
+                let sig = ed25519_dalek::Signature::from_slice(sig).map_err(invalid)?;
+                key.verify_strict(msg, &sig).map_err(invalid)
+            }
+            Identity::Secp256k1(key) => {
+                use k256::ecdsa::{Signature, VerifyingKey};
+                let key = VerifyingKey::from_sec1_bytes(key).map_err(invalid)?;
+                let sig = Signature::from_slice(sig)
+                    .or_else(|_| Signature::from_der(sig))
+                    .map_err(invalid)?;
+                key.verify(msg, &sig).map_err(invalid)
+            }
+            Identity::P256(key) => {
+                use p256::ecdsa::{Signature, VerifyingKey};
+                let key = VerifyingKey::from_sec1_bytes(key).map_err(invalid)?;
+                let sig = Signature::from_slice(sig)
+                    .or_else(|_| Signature::from_der(sig))
+                    .map_err(invalid)?;
+                key.verify(msg, &sig).map_err(invalid)
+            }
+            _ => Err(invalid("identity is not a public key")),
+        }
+    }
+}
+
+impl From<ed25519_dalek::VerifyingKey> for Identity {
+    fn from(key: ed25519_dalek::VerifyingKey) -> Self {
+        Identity::Ed25519(Bytes::copy_from_slice(key.as_bytes()))
+    }
+}
+
+impl From<k256::ecdsa::VerifyingKey> for Identity {
+    fn from(key: k256::ecdsa::VerifyingKey) -> Self {
+        Identity::Secp256k1(Bytes::copy_from_slice(
+            key.to_encoded_point(true).as_bytes(),
+        ))
+    }
+}
+
+impl From<p256::ecdsa::VerifyingKey> for Identity {
+    fn from(key: p256::ecdsa::VerifyingKey) -> Self {
+        Identity::P256(Bytes::copy_from_slice(
+            key.to_encoded_point(true).as_bytes(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::addr::StackAddr;
+    use ed25519_dalek::Signer;
+
+    #[test]
+    fn test_ed25519_verify() {
+        let key = ed25519_dalek::SigningKey::from_bytes(&[7; 32]);
+        let id = Identity::from(key.verifying_key());
+        let sig = key.sign(b"msg").to_bytes();
+        assert!(id.verify(b"msg", &sig).is_ok());
+        assert!(matches!(
+            id.verify(b"other", &sig),
+            Err(StackAddrError::SignatureInvalid(_))
+        ));
+        assert!(id.verify(b"msg", &sig[..63]).is_err());
+
+        let addr: StackAddr = format!("/ip4/127.0.0.1/tcp/4001{}", id).parse().unwrap();
+        assert_eq!(addr.get_identity(), Some(&id));
+    }
+
+    #[test]
+    fn test_ecdsa_verify() {
+        let k1 = k256::ecdsa::SigningKey::from_slice(&[7; 32]).unwrap();
+        let id = Identity::from(*k1.verifying_key());
+        let sig: k256::ecdsa::Signature = k1.sign(b"msg");
+        assert!(id.verify(b"msg", &sig.to_bytes()).is_ok());
+        assert!(id.verify(b"msg", sig.to_der().as_bytes()).is_ok());
+        assert!(id.verify(b"other", &sig.to_bytes()).is_err());
+
+        let p = p256::ecdsa::SigningKey::from_slice(&[7; 32]).unwrap();
+        let id = Identity::from(*p.verifying_key());
+        assert_eq!(id.id_bytes().len(), 33);
+        let sig: p256::ecdsa::Signature = p.sign(b"msg");
+        assert!(id.verify(b"msg", &sig.to_bytes()).is_ok());
+        // A secp256k1 signature does not verify under a P-256 key.
+        let other: k256::ecdsa::Signature = k1.sign(b"msg");
+        assert!(id.verify(b"msg", &other.to_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_point_validation() {
+        // 0x02 prefix with an x coordinate outside the field.
+        let mut off_curve = [0xff; 33];
+        off_curve[0] = 0x02;
+        let s = format!(
+            "/secp256k1/{}",
+            base32::encode(base32::Alphabet::Rfc4648 { padding: false }, &off_curve)
+        );
+        let err = s.parse::<StackAddr>().unwrap_err();
+        assert!(matches!(err.kind(), StackAddrError::InvalidValue { .. }));
+
+        let id = Identity::NodeId(Bytes::from_static(&[1; 32]));
+        assert!(id.verify(b"msg", &[0; 64]).is_err());
+    }
+}
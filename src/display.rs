@@ -0,0 +1,191 @@
+//! Configurable string output
+//!
+//! [`StackAddr`]'s `Display` writes the canonical form, which is what the parser and
+//! other tools expect. [`StackAddr::display_with`] takes [`DisplayOptions`] to write
+//! a variant meant for people instead, e.g. with hyphenated UUIDs:
+//!
+//! ```rust
+//! use stackaddr::{DisplayOptions, StackAddr, UuidFormat};
+//!
+//! let addr: StackAddr = "/ip4/10.0.0.1/tcp/443/uuid/urn:uuid:550e8400-e29b-41d4-a716-446655440000"
+//!     .parse()
+//!     .unwrap();
+//! assert_eq!(addr.to_string(), "/ip4/10.0.0.1/tcp/443/uuid/550e8400e29b41d4a716446655440000");
+//!
+//! let opts = DisplayOptions::new().with_uuid_format(UuidFormat::Hyphenated);
+//! assert_eq!(
+//!     addr.display_with(&opts).to_string(),
+//!     "/ip4/10.0.0.1/tcp/443/uuid/550e8400-e29b-41d4-a716-446655440000"
+//! );
+//! ```
+//!
+//! Every output style parses back to the same address. Addresses with user-defined
+//! protocols should be written with [`DisplayOptions::with_registry`], so that paths
+//! named like a registered protocol stay paths when parsed with that registry.
+
+use std::fmt;
+
+use crate::{
+    addr::StackAddr,
+    registry::ProtocolRegistry,
+    segment::{Segment, escape, escape_leading, escape_path, identity::Identity},
+};
+
+/// How UUID identities are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum UuidFormat {
+    /// 32 hex digits without hyphens (the canonical form)
+    #[default]
+    Simple,
+    /// `8-4-4-4-12` hex digits separated by hyphens
+    Hyphenated,
+    /// Hyphenated and wrapped in braces, as printed by Windows tooling
+    Braced,
+    /// Hyphenated with a `urn:uuid:` prefix
+    Urn,
+}
+
+/// Options for writing the string form.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DisplayOptions<'a> {
+    uuid_format: UuidFormat,
+    explicit_paths: bool,
+    registry: Option<&'a ProtocolRegistry>,
+}
+
+impl<'a> DisplayOptions<'a> {
+    /// Create options matching `Display`: the canonical form.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set how UUID identities are written.
+    pub fn with_uuid_format(mut self, format: UuidFormat) -> Self {
+        self.uuid_format = format;
+        self
+    }
+
+    /// Write paths as `/path/<p>`, the form required by strict parsing.
+    ///
+    /// The result is meant for [`ParseOptions::with_strict`](crate::ParseOptions::with_strict);
+    /// lenient parsing reads each `path` word as a path of its own.
+    pub fn with_explicit_paths(mut self, explicit_paths: bool) -> Self {
+        self.explicit_paths = explicit_paths;
+        self
+    }
+
+    /// Escape paths that collide with a protocol described by `registry`.
+    pub fn with_registry(mut self, registry: &'a ProtocolRegistry) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Returns how UUID identities are written.
+    pub fn uuid_format(&self) -> UuidFormat {
+        self.uuid_format
+    }
+
+    /// Check if paths are written as `/path/<p>`.
+    pub fn explicit_paths(&self) -> bool {
+        self.explicit_paths
+    }
+
+    /// Returns the registry whose protocol names are escaped in paths.
+    pub fn registry(&self) -> Option<&'a ProtocolRegistry> {
+        self.registry
+    }
+}
+
+/// Writes a [`StackAddr`] with [`DisplayOptions`], see [`StackAddr::display_with`].
+#[derive(Debug, Clone, Copy)]
+pub struct StackAddrDisplay<'a> {
+    addr: &'a StackAddr,
+    options: DisplayOptions<'a>,
+}
+
+impl<'a> StackAddrDisplay<'a> {
+    pub(crate) fn new(addr: &'a StackAddr, options: &DisplayOptions<'a>) -> Self {
+        StackAddrDisplay {
+            addr,
+            options: *options,
+        }
+    }
+}
+
+impl fmt::Display for StackAddrDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for seg in self.addr.segments() {
+            match seg {
+                Segment::Identity(Identity::Uuid(uuid)) => match self.options.uuid_format {
+                    UuidFormat::Simple => write!(f, "/uuid/{}", uuid.simple())?,
+                    UuidFormat::Hyphenated => write!(f, "/uuid/{}", uuid.hyphenated())?,
+                    UuidFormat::Braced => write!(f, "/uuid/{}", uuid.braced())?,
+                    UuidFormat::Urn => write!(f, "/uuid/{}", uuid.urn())?,
+                },
+                Segment::Path(path) if self.options.explicit_paths => {
+                    write!(f, "/path/{}", escape(path))?
+                }
+                Segment::Path(path)
+                    if self.options.registry.is_some_and(|r| r.get(path).is_some()) =>
+                {
+                    write!(f, "/{}", escape_leading(path))?
+                }
+                Segment::Path(path) => write!(f, "/{}", escape_path(path))?,
+                seg => write!(f, "{}", seg)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ParseOptions;
+
+    #[test]
+    fn test_uuid_formats_roundtrip() {
+        let addr: StackAddr = "/ip4/10.0.0.1/tcp/443/uuid/550e8400e29b41d4a716446655440000"
+            .parse()
+            .unwrap();
+        for (format, expected) in [
+            (UuidFormat::Simple, "550e8400e29b41d4a716446655440000"),
+            (
+                UuidFormat::Hyphenated,
+                "550e8400-e29b-41d4-a716-446655440000",
+            ),
+            (UuidFormat::Braced, "{550e8400-e29b-41d4-a716-446655440000}"),
+            (
+                UuidFormat::Urn,
+                "urn:uuid:550e8400-e29b-41d4-a716-446655440000",
+            ),
+        ] {
+            let s = addr
+                .display_with(&DisplayOptions::new().with_uuid_format(format))
+                .to_string();
+            assert_eq!(s, format!("/ip4/10.0.0.1/tcp/443/uuid/{}", expected));
+            assert_eq!(s.parse::<StackAddr>().unwrap(), addr);
+        }
+        assert_eq!(
+            addr.display_with(&DisplayOptions::new()).to_string(),
+            addr.to_string()
+        );
+    }
+
+    #[test]
+    fn test_explicit_paths_parse_strict() {
+        let addr: StackAddr = "/dns/example.com/tcp/443/images/%70ath/a%2Fb"
+            .parse()
+            .unwrap();
+        let s = addr
+            .display_with(&DisplayOptions::new().with_explicit_paths(true))
+            .to_string();
+        assert_eq!(
+            s,
+            "/dns/example.com/tcp/443/path/images/path/path/path/a%2Fb"
+        );
+        let strict = ParseOptions::new().with_strict(true);
+        assert_eq!(StackAddr::parse_with_options(&s, &strict).unwrap(), addr);
+        assert!(StackAddr::parse_with_options(&addr.to_string(), &strict).is_err());
+    }
+}
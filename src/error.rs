@@ -1,9 +1,11 @@
-use std::fmt;
-use std::net::AddrParseError;
-use std::num::ParseIntError;
+use crate::{segment::protocol::Protocol, validate::Violation};
+use std::net::{AddrParseError, IpAddr};
+use std::ops::Range;
+use std::{fmt, io};
 
 /// An error that can occur when parsing a StackAddr string.
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum StackAddrError {
     /// A required part of the address was missing.
     MissingPart(&'static str),
@@ -11,8 +13,24 @@ pub enum StackAddrError {
     /// Failed to parse an IP address.
     InvalidIp(AddrParseError),
 
-    /// Failed to parse a port number.
-    InvalidPort(ParseIntError),
+    /// A port is not a number, e.g. `/tcp/8o`.
+    InvalidPort { protocol: String, raw: String },
+
+    /// A value is well-formed but not acceptable, e.g. a port above 65535.
+    InvalidValue {
+        protocol: String,
+        raw: String,
+        reason: String,
+    },
+
+    /// The input continues after a complete address, e.g. a trailing newline.
+    UnexpectedTrailing(String),
+
+    /// The input starts with whitespace before the address.
+    UnexpectedLeading(String),
+
+    /// The address has no segments.
+    EmptyAddress,
 
     /// Unknown protocol encountered.
     UnknownProtocol(String),
@@ -22,6 +40,60 @@ pub enum StackAddrError {
 
     /// Name resolution failed when trying to derive socket addresses.
     ResolutionFailed(String),
+
+    /// A segment or protocol has no equivalent in the target representation.
+    NoEquivalent(String),
+
+    /// A protocol could not be added to a [`ProtocolRegistry`](crate::ProtocolRegistry).
+    RegistryConflict(String),
+
+    /// A socket operation failed, e.g. in [`net::connect_tcp`](crate::net::connect_tcp).
+    ///
+    /// Keeps the [`io::ErrorKind`] and message of the underlying [`io::Error`].
+    Io {
+        kind: io::ErrorKind,
+        message: String,
+    },
+
+    /// A signature did not verify against an identity.
+    SignatureInvalid(String),
+
+    /// A signed record is past its expiry time.
+    RecordExpired,
+
+    /// The protocol stack is not layered correctly.
+    InvalidStack(Vec<Violation>),
+
+    /// An IP address does not match the family required by a DNS segment (e.g. IPv6 for `/dns4`).
+    FamilyMismatch { protocol: Protocol, ip: IpAddr },
+
+    /// A string form address could not be parsed.
+    ///
+    /// Wraps the underlying error (e.g. [`StackAddrError::InvalidIp`]) as `kind`, together
+    /// with the location of the offending segment. Its `Display` renders the input line
+    /// holding that segment with a caret under it.
+    ParseError {
+        /// The address string that failed to parse.
+        input: String,
+        /// Index of the segment that failed to parse.
+        segment_index: usize,
+        /// Byte range of the offending part within `input`.
+        byte_range: Range<usize>,
+        /// The protocol word of the segment (e.g. `ip4`), or `None` for paths.
+        protocol: Option<String>,
+        /// The underlying error.
+        kind: Box<StackAddrError>,
+    },
+}
+
+impl StackAddrError {
+    /// Returns the underlying error, looking through [`StackAddrError::ParseError`].
+    pub fn kind(&self) -> &StackAddrError {
+        match self {
+            StackAddrError::ParseError { kind, .. } => kind.kind(),
+            other => other,
+        }
+    }
 }
 
 impl fmt::Display for StackAddrError {
@@ -29,7 +101,21 @@ impl fmt::Display for StackAddrError {
         match self {
             StackAddrError::MissingPart(what) => write!(f, "Missing {}", what),
             StackAddrError::InvalidIp(e) => write!(f, "Invalid IP address: {}", e),
-            StackAddrError::InvalidPort(e) => write!(f, "Invalid port: {}", e),
+            StackAddrError::InvalidPort { protocol, raw } => {
+                write!(f, "Invalid {} port: {:?}", protocol, raw)
+            }
+            StackAddrError::InvalidValue {
+                protocol,
+                raw,
+                reason,
+            } => write!(f, "Invalid {} value {:?}: {}", protocol, raw, reason),
+            StackAddrError::UnexpectedTrailing(rest) => {
+                write!(f, "Unexpected trailing input: {:?}", rest)
+            }
+            StackAddrError::UnexpectedLeading(rest) => {
+                write!(f, "Unexpected leading input: {:?}", rest)
+            }
+            StackAddrError::EmptyAddress => write!(f, "Empty address"),
             StackAddrError::UnknownProtocol(p) => write!(f, "Unknown protocol: {}", p),
             StackAddrError::InvalidEncoding(e) => write!(f, "Invalid encoding: {}", e),
             StackAddrError::ResolutionFailed(e) => {
@@ -39,20 +125,81 @@ impl fmt::Display for StackAddrError {
                     e
                 )
             }
+            StackAddrError::NoEquivalent(what) => {
+                write!(f, "No equivalent representation for {}", what)
+            }
+            StackAddrError::RegistryConflict(e) => {
+                write!(f, "Protocol registration conflict: {}", e)
+            }
+            StackAddrError::Io { message, .. } => write!(f, "I/O error: {}", message),
+            StackAddrError::SignatureInvalid(e) => {
+                write!(f, "Invalid signature: {}", e)
+            }
+            StackAddrError::RecordExpired => write!(f, "Signed record has expired"),
+            StackAddrError::InvalidStack(violations) => {
+                write!(f, "Invalid stack: ")?;
+                for (i, v) in violations.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "; ")?;
+                    }
+                    write!(f, "{}", v)?;
+                }
+                Ok(())
+            }
+            StackAddrError::FamilyMismatch { protocol, ip } => {
+                write!(f, "IP address {} does not match {}", ip, protocol)
+            }
+            StackAddrError::ParseError {
+                input,
+                segment_index,
+                byte_range,
+                protocol,
+                kind,
+            } => {
+                write!(f, "{} at segment {}", kind, segment_index)?;
+                if let Some(protocol) = protocol {
+                    write!(f, " ({})", protocol)?;
+                }
+                // Show only the line holding the segment, with the caret counted in chars.
+                let start = byte_range.start;
+                let line_start = input[..start].rfind('\n').map_or(0, |i| i + 1);
+                let line_end = input[start..].find('\n').map_or(input.len(), |i| start + i);
+                let offset = input[line_start..start].chars().count();
+                let end = byte_range.end.min(line_end).max(start);
+                let width = input[start..end].chars().count().max(1);
+                write!(
+                    f,
+                    "\n{}\n{}{}",
+                    &input[line_start..line_end],
+                    " ".repeat(offset),
+                    "^".repeat(width)
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for StackAddrError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StackAddrError::ParseError { kind, .. } => Some(kind.as_ref()),
+            StackAddrError::InvalidIp(e) => Some(e),
+            _ => None,
         }
     }
 }
 
-impl std::error::Error for StackAddrError {}
+impl From<io::Error> for StackAddrError {
+    fn from(e: io::Error) -> Self {
+        StackAddrError::Io {
+            kind: e.kind(),
+            message: e.to_string(),
+        }
+    }
+}
 
 impl From<AddrParseError> for StackAddrError {
     fn from(e: AddrParseError) -> Self {
         StackAddrError::InvalidIp(e)
     }
 }
-
-impl From<ParseIntError> for StackAddrError {
-    fn from(e: ParseIntError) -> Self {
-        StackAddrError::InvalidPort(e)
-    }
-}
@@ -8,10 +8,24 @@
 //! - Multi-layered address structure (L2-L7)
 //! - Protocol segments: `/ip4/127.0.0.1/tcp/443/tls/http`
 //!     - Also, supports L2 MAC addresses like `/mac/aa:bb:cc:dd:ee:ff`.
+//!     - Unix domain sockets like `/unix/%2Frun%2Fagent.sock/http`.
 //! - Identity segments: `/node/<base32>`, `/uuid/<uuid>`
+//!     - Identity values are also accepted in multibase form (`/peer/z...`), see [`Multibase`].
 //! - Metadata and path support
-//! - `Display` and `FromStr` support
-//! - Optional Serde serialization (`serde` feature)
+//! - User-defined protocols via [`ProtocolRegistry`]
+//! - Stack layering validation (`validate` / `parse_strict`)
+//! - `Display` and `FromStr` support, with a strict mode via [`ParseOptions`]
+//! - Compact binary encoding (`to_bytes` / `from_bytes`)
+//! - Zero-copy borrowed views ([`StackAddrRef`])
+//! - Blocking `std::net` connect and bind helpers ([`net`])
+//! - Optional Serde serialization (`serde` feature), as the string form for
+//!   human-readable formats and the binary form otherwise
+//! - Optional libp2p multiaddr conversions (`multiaddr` feature)
+//! - Optional URL conversions (`from_url` / `to_url`, `url` feature)
+//! - Optional multihash wrapping of identities (`multihash` feature)
+//! - Typed public key identities (`/ed25519/...`, `/secp256k1/...`, `/p256/...`), with
+//!   curve point checks and signature verification (`crypto` feature)
+//! - Signed address records ([`SignedStackAddr`], `crypto` feature)
 //!
 //! ## Example
 //! ```rust
@@ -34,13 +48,65 @@ pub mod addr;
 /// Segment definitions, including protocol, identity, metadata, and path.
 pub mod segment;
 
+/// Zero-copy borrowed views of [`StackAddr`].
+pub mod borrowed;
+
+/// Parsing of the string form.
+pub mod parse;
+
+/// Blocking sockets from [`StackAddr`]s.
+pub mod net;
+
+/// Configurable string output of [`StackAddr`].
+pub mod display;
+
+/// Compact binary encoding of [`StackAddr`].
+pub mod codec;
+
+/// Serde support for [`StackAddr`] (`serde` feature).
+#[cfg(feature = "serde")]
+mod serde;
+
+/// Public key identities and signature verification (`crypto` feature).
+#[cfg(feature = "crypto")]
+mod crypto;
+
+/// Signed address records (`crypto` feature).
+#[cfg(feature = "crypto")]
+pub mod signed;
+
+/// Conversions to and from `http`, `https`, `ws` and `wss` URLs (`url` feature).
+#[cfg(feature = "url")]
+mod url;
+
+/// Conversions to and from libp2p multiaddr (`multiaddr` feature).
+#[cfg(feature = "multiaddr")]
+mod multiaddr;
+
+/// Registry for user-defined protocols.
+pub mod registry;
+
+/// Layering validation for protocol stacks.
+pub mod validate;
+
+/// Pluggable name resolution for DNS segments.
+pub mod resolver;
+
 /// Error types used in [`StackAddr`] and related parsing operations.
 pub mod error;
 
 pub use addr::StackAddr;
+pub use borrowed::{IdentityRef, ProtocolRef, SegmentRef, StackAddrRef};
+pub use display::{DisplayOptions, UuidFormat};
 pub use error::StackAddrError;
+pub use parse::ParseOptions;
+pub use registry::{ProtocolRegistry, ProtocolSpec};
+pub use resolver::{AsyncResolver, Resolver, StaticResolver};
 pub use segment::Segment;
-pub use segment::identity::Identity;
-pub use segment::protocol::Protocol;
+pub use segment::identity::{Identity, IdentityKey, KeyType, Multibase};
+pub use segment::protocol::{Layer, Protocol, TransportKind, TransportProtocol};
+#[cfg(feature = "crypto")]
+pub use signed::{AddrSigner, SignedStackAddr};
+pub use validate::Violation;
 
 pub use mac_addr::MacAddr;
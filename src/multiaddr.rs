@@ -0,0 +1,362 @@
+//! Multiaddr interoperability
+//!
+//! Conversions between [`StackAddr`] and libp2p's [`Multiaddr`](::multiaddr::Multiaddr),
+//! available with the `multiaddr` feature.
+//!
+//! The mapping is mostly one-to-one, with a few differences in how the two
+//! formats express the same stack:
+//!
+//! - `/quic` maps to `/quic-v1` (legacy `/quic` is also accepted from multiaddr)
+//! - `/ws/<port>`, `/wss/<port>` and `/wtr/<port>` carry their own port in stackaddr,
+//!   while multiaddr takes it from the preceding `/tcp` or `/udp` segment
+//! - `/peer/<id>` maps to `/p2p/<id>` and must hold a valid libp2p peer id
+//!
+//! Anything without a counterpart (e.g. `/mac`, `/node`, `/uuid`, paths and metadata)
+//! is rejected with [`StackAddrError::NoEquivalent`].
+
+use ::multiaddr::{Multiaddr, PeerId, Protocol as MaProtocol};
+use bytes::Bytes;
+use std::{borrow::Cow, path::PathBuf};
+
+use crate::{
+    addr::StackAddr,
+    error::StackAddrError,
+    segment::{Segment, identity::Identity, protocol::Protocol},
+};
+
+/// Parses a stackaddr onion value (`<base32>:<port>`) with multiaddr's own parser.
+fn onion_to_multiaddr(addr: &str) -> Result<MaProtocol<'static>, StackAddrError> {
+    let host = addr.split(':').next().unwrap_or_default();
+    let name = if host.len() == 16 { "onion" } else { "onion3" };
+    let parsed: Multiaddr = format!("/{}/{}", name, addr)
+        .parse()
+        .map_err(|_| StackAddrError::InvalidEncoding("onion address"))?;
+    parsed
+        .iter()
+        .next()
+        .map(|p| p.acquire())
+        .ok_or(StackAddrError::InvalidEncoding("onion address"))
+}
+
+/// Renders a multiaddr onion protocol back into the stackaddr onion value.
+fn onion_from_multiaddr(p: &MaProtocol<'_>) -> String {
+    let text = p.to_string();
+    text.trim_start_matches("/onion3/")
+        .trim_start_matches("/onion/")
+        .to_string()
+}
+
+impl TryFrom<&StackAddr> for Multiaddr {
+    type Error = StackAddrError;
+
+    fn try_from(addr: &StackAddr) -> Result<Self, Self::Error> {
+        let mut out = Multiaddr::empty();
+        let mut tcp_port = None;
+        let mut udp_port = None;
+        let mut quic = false;
+
+        for seg in addr.segments() {
+            match seg {
+                Segment::Protocol(p) => match p {
+                    Protocol::Ip4(ip) => out.push(MaProtocol::Ip4(*ip)),
+                    Protocol::Ip6(ip) => out.push(MaProtocol::Ip6(*ip)),
+                    Protocol::Ip6Zone(zone) => out.push(MaProtocol::Ip6zone(Cow::Borrowed(zone))),
+                    Protocol::Dns(name) => out.push(MaProtocol::Dns(Cow::Borrowed(name))),
+                    Protocol::Dns4(name) => out.push(MaProtocol::Dns4(Cow::Borrowed(name))),
+                    Protocol::Dns6(name) => out.push(MaProtocol::Dns6(Cow::Borrowed(name))),
+                    Protocol::Unix(path) => match path.to_str() {
+                        Some(path) => out.push(MaProtocol::Unix(Cow::Borrowed(path))),
+                        None => return Err(StackAddrError::NoEquivalent(seg.to_string())),
+                    },
+                    Protocol::Tcp(port) => {
+                        tcp_port = Some(*port);
+                        out.push(MaProtocol::Tcp(*port));
+                    }
+                    Protocol::Udp(port) => {
+                        udp_port = Some(*port);
+                        out.push(MaProtocol::Udp(*port));
+                    }
+                    Protocol::Tls => out.push(MaProtocol::Tls),
+                    Protocol::Quic => {
+                        quic = true;
+                        out.push(MaProtocol::QuicV1);
+                    }
+                    Protocol::Http => out.push(MaProtocol::Http),
+                    Protocol::Https => out.push(MaProtocol::Https),
+                    Protocol::Ws(port) | Protocol::Wss(port) => {
+                        match tcp_port {
+                            Some(tcp) if tcp != *port => {
+                                return Err(StackAddrError::NoEquivalent(format!(
+                                    "{} over tcp port {}",
+                                    p, tcp
+                                )));
+                            }
+                            Some(_) => {}
+                            None => {
+                                tcp_port = Some(*port);
+                                out.push(MaProtocol::Tcp(*port));
+                            }
+                        }
+                        if matches!(p, Protocol::Ws(_)) {
+                            out.push(MaProtocol::Ws(Cow::Borrowed("/")));
+                        } else {
+                            out.push(MaProtocol::Wss(Cow::Borrowed("/")));
+                        }
+                    }
+                    Protocol::WebTransport(port) => {
+                        match udp_port {
+                            Some(udp) if udp != *port => {
+                                return Err(StackAddrError::NoEquivalent(format!(
+                                    "{} over udp port {}",
+                                    p, udp
+                                )));
+                            }
+                            Some(_) => {}
+                            None => {
+                                udp_port = Some(*port);
+                                out.push(MaProtocol::Udp(*port));
+                            }
+                        }
+                        if !quic {
+                            quic = true;
+                            out.push(MaProtocol::QuicV1);
+                        }
+                        out.push(MaProtocol::WebTransport);
+                    }
+                    Protocol::WebRTC => out.push(MaProtocol::WebRTC),
+                    Protocol::Onion(addr) => out.push(onion_to_multiaddr(addr)?),
+                    Protocol::Mac(_) | Protocol::Custom(_) | Protocol::Registered { .. } => {
+                        return Err(StackAddrError::NoEquivalent(p.to_string()));
+                    }
+                },
+                Segment::Identity(Identity::PeerId(id)) => {
+                    let peer = PeerId::from_bytes(id)
+                        .map_err(|_| StackAddrError::InvalidEncoding("libp2p peer id"))?;
+                    out.push(MaProtocol::P2p(peer));
+                }
+                other => return Err(StackAddrError::NoEquivalent(other.to_string())),
+            }
+        }
+
+        Ok(out)
+    }
+}
+
+impl TryFrom<StackAddr> for Multiaddr {
+    type Error = StackAddrError;
+
+    fn try_from(addr: StackAddr) -> Result<Self, Self::Error> {
+        Multiaddr::try_from(&addr)
+    }
+}
+
+impl TryFrom<&Multiaddr> for StackAddr {
+    type Error = StackAddrError;
+
+    fn try_from(addr: &Multiaddr) -> Result<Self, Self::Error> {
+        let mut out = StackAddr::empty();
+        let mut tcp_port = None;
+        let mut udp_port = None;
+
+        for p in addr.iter() {
+            let proto = match p {
+                MaProtocol::Ip4(ip) => Protocol::Ip4(ip),
+                MaProtocol::Ip6(ip) => Protocol::Ip6(ip),
+                MaProtocol::Ip6zone(ref zone) => Protocol::Ip6Zone(zone.to_string()),
+                MaProtocol::Dns(ref name) => Protocol::Dns(name.to_string()),
+                MaProtocol::Dns4(ref name) => Protocol::Dns4(name.to_string()),
+                MaProtocol::Dns6(ref name) => Protocol::Dns6(name.to_string()),
+                MaProtocol::Unix(ref path) => Protocol::Unix(PathBuf::from(path.as_ref())),
+                MaProtocol::Tcp(port) => {
+                    tcp_port = Some(port);
+                    Protocol::Tcp(port)
+                }
+                MaProtocol::Udp(port) => {
+                    udp_port = Some(port);
+                    Protocol::Udp(port)
+                }
+                MaProtocol::Tls => Protocol::Tls,
+                MaProtocol::Quic | MaProtocol::QuicV1 => Protocol::Quic,
+                MaProtocol::Http => Protocol::Http,
+                MaProtocol::Https => Protocol::Https,
+                MaProtocol::Ws(ref path) | MaProtocol::Wss(ref path) if path == "/" => {
+                    let port =
+                        tcp_port.ok_or(StackAddrError::MissingPart("tcp port for websocket"))?;
+                    if matches!(p, MaProtocol::Ws(_)) {
+                        Protocol::Ws(port)
+                    } else {
+                        Protocol::Wss(port)
+                    }
+                }
+                MaProtocol::WebTransport => Protocol::WebTransport(
+                    udp_port.ok_or(StackAddrError::MissingPart("udp port for webtransport"))?,
+                ),
+                MaProtocol::WebRTC => Protocol::WebRTC,
+                MaProtocol::Onion(..) | MaProtocol::Onion3(_) => {
+                    Protocol::Onion(onion_from_multiaddr(&p))
+                }
+                MaProtocol::P2p(peer) => {
+                    out.push(Segment::Identity(Identity::PeerId(Bytes::from(
+                        peer.to_bytes(),
+                    ))));
+                    continue;
+                }
+                other => return Err(StackAddrError::NoEquivalent(other.to_string())),
+            };
+            out.push(Segment::Protocol(proto));
+        }
+
+        Ok(out)
+    }
+}
+
+impl TryFrom<Multiaddr> for StackAddr {
+    type Error = StackAddrError;
+
+    fn try_from(addr: Multiaddr) -> Result<Self, Self::Error> {
+        StackAddr::try_from(&addr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a libp2p peer id from an Ed25519 public key (identity multihash).
+    fn ed25519_peer_id() -> PeerId {
+        let mut bytes = vec![0x00, 0x24, 0x08, 0x01, 0x12, 0x20];
+        bytes.extend_from_slice(&[7u8; 32]);
+        PeerId::from_bytes(&bytes).unwrap()
+    }
+
+    #[test]
+    fn test_quic_peer_roundtrip() {
+        let peer = ed25519_peer_id();
+        let ma: Multiaddr = format!("/ip4/192.168.10.10/udp/4433/quic-v1/p2p/{}", peer)
+            .parse()
+            .unwrap();
+
+        let addr = StackAddr::try_from(&ma).unwrap();
+        let expected = StackAddr::empty()
+            .with_protocol(Protocol::Ip4("192.168.10.10".parse().unwrap()))
+            .with_protocol(Protocol::Udp(4433))
+            .with_protocol(Protocol::Quic)
+            .with_identity(Identity::PeerId(Bytes::from(peer.to_bytes())));
+        assert_eq!(addr, expected);
+
+        let back = Multiaddr::try_from(&addr).unwrap();
+        assert_eq!(back, ma);
+    }
+
+    #[test]
+    fn test_legacy_quic_accepted() {
+        let ma: Multiaddr = "/ip6/::1/udp/4433/quic".parse().unwrap();
+        let addr = StackAddr::try_from(ma).unwrap();
+        assert_eq!(addr.to_string(), "/ip6/::1/udp/4433/quic");
+    }
+
+    #[test]
+    fn test_ip6zone_roundtrip() {
+        let ma: Multiaddr = "/ip6zone/eth0/ip6/fe80::1/tcp/4001".parse().unwrap();
+        let addr = StackAddr::try_from(&ma).unwrap();
+        assert_eq!(addr.to_string(), "/ip6zone/eth0/ip6/fe80::1/tcp/4001");
+        assert_eq!(Multiaddr::try_from(&addr).unwrap(), ma);
+    }
+
+    #[test]
+    fn test_unix_roundtrip() {
+        let addr: StackAddr = "/unix/%2Frun%2Fagent.sock/http".parse().unwrap();
+        let ma = Multiaddr::try_from(&addr).unwrap();
+        assert_eq!(
+            ma.iter().next(),
+            Some(MaProtocol::Unix(Cow::Borrowed("/run/agent.sock")))
+        );
+        assert_eq!(StackAddr::try_from(&ma).unwrap(), addr);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+
+            let path = std::ffi::OsStr::from_bytes(b"/tmp/a\xffb.sock");
+            let addr = StackAddr::empty().with_unix_path(path);
+            assert!(matches!(
+                Multiaddr::try_from(&addr),
+                Err(StackAddrError::NoEquivalent(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_websocket_port_from_tcp() {
+        let ma: Multiaddr = "/dns4/example.com/tcp/443/wss".parse().unwrap();
+        let addr = StackAddr::try_from(&ma).unwrap();
+        assert_eq!(addr.to_string(), "/dns4/example.com/tcp/443/wss/443");
+        assert_eq!(Multiaddr::try_from(&addr).unwrap(), ma);
+
+        // A bare websocket port gains an explicit tcp segment.
+        let addr: StackAddr = "/ip4/127.0.0.1/ws/8080".parse().unwrap();
+        let ma = Multiaddr::try_from(&addr).unwrap();
+        assert_eq!(ma.to_string(), "/ip4/127.0.0.1/tcp/8080/ws");
+    }
+
+    #[test]
+    fn test_websocket_port_mismatch() {
+        let addr: StackAddr = "/ip4/127.0.0.1/tcp/80/ws/8080".parse().unwrap();
+        assert!(matches!(
+            Multiaddr::try_from(&addr),
+            Err(StackAddrError::NoEquivalent(_))
+        ));
+    }
+
+    #[test]
+    fn test_webtransport() {
+        let ma: Multiaddr = "/ip4/127.0.0.1/udp/4433/quic-v1/webtransport"
+            .parse()
+            .unwrap();
+        let addr = StackAddr::try_from(&ma).unwrap();
+        assert_eq!(addr.to_string(), "/ip4/127.0.0.1/udp/4433/quic/wtr/4433");
+        assert_eq!(Multiaddr::try_from(&addr).unwrap(), ma);
+    }
+
+    #[test]
+    fn test_onion3_roundtrip() {
+        let onion = "vww6ybal4bd7szmgncyruucpgfkqahzddi37ktceo3ah7ngmcopnpyyd:1234";
+        let ma: Multiaddr = format!("/onion3/{}", onion).parse().unwrap();
+        let addr = StackAddr::try_from(&ma).unwrap();
+        assert_eq!(
+            addr.segments(),
+            &[Segment::Protocol(Protocol::Onion(onion.into()))]
+        );
+        assert_eq!(Multiaddr::try_from(&addr).unwrap(), ma);
+    }
+
+    #[test]
+    fn test_no_equivalent() {
+        let addr: StackAddr = "/mac/aa:bb:cc:dd:ee:ff/ip4/10.0.0.1".parse().unwrap();
+        assert!(matches!(
+            Multiaddr::try_from(&addr),
+            Err(StackAddrError::NoEquivalent(_))
+        ));
+
+        let addr: StackAddr = "/ip4/10.0.0.1/tcp/80/meta/env/prod".parse().unwrap();
+        assert!(matches!(
+            Multiaddr::try_from(&addr),
+            Err(StackAddrError::NoEquivalent(_))
+        ));
+
+        let ma: Multiaddr = "/ip4/10.0.0.1/tcp/80/p2p-circuit".parse().unwrap();
+        assert!(matches!(
+            StackAddr::try_from(&ma),
+            Err(StackAddrError::NoEquivalent(_))
+        ));
+    }
+
+    #[test]
+    fn test_invalid_peer_id() {
+        let addr = StackAddr::empty().with_identity(Identity::PeerId(Bytes::from_static(&[1, 2])));
+        assert!(matches!(
+            Multiaddr::try_from(&addr),
+            Err(StackAddrError::InvalidEncoding(_))
+        ));
+    }
+}
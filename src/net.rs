@@ -0,0 +1,150 @@
+//! Blocking socket helpers
+//!
+//! Opens `std::net` sockets for a [`StackAddr`]: the address is resolved with
+//! [`StackAddr::socket_addrs`] and every resolved address is tried in order until one
+//! succeeds. The transport of the stack decides which sockets it can be used for:
+//! `/tcp`, `/tls`, `/ws` and `/wss` stacks are TCP-based, `/udp`, `/quic` and `/wtr`
+//! stacks are UDP-based. Anything else is rejected with a [`StackAddrError`].
+//!
+//! Protocols above the transport (TLS, HTTP, ...) are left to the caller.
+//!
+//! ```rust
+//! use stackaddr::{StackAddr, net};
+//!
+//! let listen: StackAddr = "/ip4/127.0.0.1/tcp/0".parse().unwrap();
+//! let listener = net::bind_tcp(&listen).unwrap();
+//! let addr = StackAddr::from(listener.local_addr().unwrap());
+//!
+//! let stream = net::connect_tcp(&addr, std::time::Duration::from_secs(1)).unwrap();
+//! assert_eq!(net::peer_stack_addr(&stream).unwrap(), addr);
+//! ```
+
+use std::{
+    io,
+    net::{SocketAddr, TcpListener, TcpStream, UdpSocket},
+    time::Duration,
+};
+
+use crate::{addr::StackAddr, error::StackAddrError, segment::protocol::TransportKind};
+
+/// Resolves `addr` after checking that its transport runs over TCP (`tcp`) or UDP.
+fn socket_addrs(addr: &StackAddr, tcp: bool) -> Result<Vec<SocketAddr>, StackAddrError> {
+    let kind = addr
+        .transport()
+        .map(|t| t.kind())
+        .ok_or(StackAddrError::MissingPart("tcp or udp transport"))?;
+    let is_tcp = matches!(
+        kind,
+        TransportKind::Tcp | TransportKind::TlsTcp | TransportKind::Ws | TransportKind::Wss
+    );
+    if is_tcp != tcp {
+        return Err(StackAddrError::NoEquivalent(format!(
+            "{} as a {} socket",
+            addr,
+            if tcp { "tcp" } else { "udp" }
+        )));
+    }
+    addr.socket_addrs()
+}
+
+/// Opens a TCP connection to `addr`, trying each resolved address in order.
+///
+/// `timeout` applies to each connection attempt and must not be zero. If every
+/// attempt fails, the error of the last one is returned.
+pub fn connect_tcp(addr: &StackAddr, timeout: Duration) -> Result<TcpStream, StackAddrError> {
+    let mut last_err = None;
+    for sock in socket_addrs(addr, true)? {
+        match TcpStream::connect_timeout(&sock, timeout) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_err = Some(e),
+        }
+    }
+    Err(last_err
+        .unwrap_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no addresses to connect"))
+        .into())
+}
+
+/// Binds a TCP listener to the first resolved address of `addr` that succeeds.
+pub fn bind_tcp(addr: &StackAddr) -> Result<TcpListener, StackAddrError> {
+    Ok(TcpListener::bind(&socket_addrs(addr, true)?[..])?)
+}
+
+/// Binds a UDP socket to the first resolved address of `addr` that succeeds.
+pub fn bind_udp(addr: &StackAddr) -> Result<UdpSocket, StackAddrError> {
+    Ok(UdpSocket::bind(&socket_addrs(addr, false)?[..])?)
+}
+
+/// Returns the local address of a TCP stream as a stack address, e.g. `/ip4/127.0.0.1/tcp/50000`.
+pub fn local_stack_addr(stream: &TcpStream) -> Result<StackAddr, StackAddrError> {
+    Ok(StackAddr::from(stream.local_addr()?))
+}
+
+/// Returns the remote address of a TCP stream as a stack address.
+pub fn peer_stack_addr(stream: &TcpStream) -> Result<StackAddr, StackAddrError> {
+    Ok(StackAddr::from(stream.peer_addr()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> StackAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_tcp_connect_and_accept() {
+        let listener = bind_tcp(&parse("/ip4/127.0.0.1/tcp/0/tls/http")).unwrap();
+        let addr = StackAddr::from(listener.local_addr().unwrap());
+
+        let client = connect_tcp(&addr, Duration::from_secs(1)).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        assert_eq!(peer_stack_addr(&client).unwrap(), addr);
+        assert_eq!(
+            local_stack_addr(&client).unwrap(),
+            peer_stack_addr(&server).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_bind_udp() {
+        let socket = bind_udp(&parse("/ip4/127.0.0.1/udp/0/quic")).unwrap();
+        let local = socket.local_addr().unwrap();
+        assert!(local.ip().is_loopback());
+        assert_ne!(local.port(), 0);
+    }
+
+    #[test]
+    fn test_transport_mismatch() {
+        assert!(matches!(
+            connect_tcp(
+                &parse("/ip4/127.0.0.1/udp/4433/quic"),
+                Duration::from_secs(1)
+            ),
+            Err(StackAddrError::NoEquivalent(_))
+        ));
+        assert!(matches!(
+            bind_udp(&parse("/ip4/127.0.0.1/tcp/0")),
+            Err(StackAddrError::NoEquivalent(_))
+        ));
+        assert_eq!(
+            bind_tcp(&parse("/unix/%2Frun%2Fagent.sock")).unwrap_err(),
+            StackAddrError::MissingPart("tcp or udp transport")
+        );
+    }
+
+    #[test]
+    fn test_connect_refused() {
+        // Bind and drop a listener to get a port that is (very likely) closed.
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let addr = StackAddr::from(SocketAddr::from(([127, 0, 0, 1], port)));
+        assert!(matches!(
+            connect_tcp(&addr, Duration::from_secs(1)),
+            Err(StackAddrError::Io { .. })
+        ));
+    }
+}
@@ -0,0 +1,335 @@
+//! String form parsing
+//!
+//! Splits the `/`-separated string form into borrowed segments. Both
+//! [`StackAddr`](crate::StackAddr)'s `FromStr` and [`StackAddrRef`](crate::StackAddrRef)
+//! are built on top of it, so the two always accept the same input.
+//!
+//! [`ParseOptions`] tunes the parser, e.g. to reject unknown words instead of
+//! treating them as paths:
+//!
+//! ```rust
+//! use stackaddr::{ParseOptions, StackAddr, StackAddrError};
+//!
+//! let opts = ParseOptions::new().with_strict(true);
+//! let err = StackAddr::parse_with_options("/ipv4/1.2.3.4/tpc/80", &opts).unwrap_err();
+//! assert_eq!(err.kind(), &StackAddrError::UnknownProtocol("ipv4".into()));
+//!
+//! let addr = StackAddr::parse_with_options("/dns/example.com/tcp/443/path/index.html", &opts).unwrap();
+//! assert_eq!(addr.to_string(), "/dns/example.com/tcp/443/index.html");
+//! ```
+//!
+//! Whitespace around the address, such as a newline read along with it, is rejected
+//! with [`StackAddrError::UnexpectedLeading`] or [`StackAddrError::UnexpectedTrailing`].
+//! Inside the address it belongs to the segment it appears in, and words after a
+//! complete stack are paths unless strict mode is enabled:
+//!
+//! ```rust
+//! use stackaddr::{Segment, StackAddr, StackAddrError};
+//!
+//! let err = " /ip4/10.0.0.1".parse::<StackAddr>().unwrap_err();
+//! assert_eq!(err.kind(), &StackAddrError::UnexpectedLeading(" ".into()));
+//! let err = "/ip4/10.0.0.1\n".parse::<StackAddr>().unwrap_err();
+//! assert_eq!(err.kind(), &StackAddrError::UnexpectedTrailing("\n".into()));
+//!
+//! let addr: StackAddr = "/ip4/10.0.0.1/a b".parse().unwrap();
+//! assert_eq!(addr.segments()[1], Segment::Path("a b".into()));
+//! ```
+
+use std::{borrow::Cow, num::IntErrorKind, ops::Range};
+
+use crate::{
+    borrowed::{IdentityRef, ProtocolRef, SegmentRef, unix_path},
+    error::StackAddrError,
+    registry::ProtocolRegistry,
+    segment::{
+        KEYWORDS,
+        identity::{KeyType, decode_id},
+        unescape, unescape_bytes,
+    },
+};
+
+/// Options for parsing the string form.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ParseOptions<'a> {
+    strict: bool,
+    registry: Option<&'a ProtocolRegistry>,
+}
+
+impl<'a> ParseOptions<'a> {
+    /// Create options matching [`FromStr`](std::str::FromStr): lenient, no registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enable or disable strict mode.
+    ///
+    /// In strict mode, words that are neither built-in nor registered protocols are
+    /// rejected with [`StackAddrError::UnknownProtocol`] instead of becoming paths,
+    /// and paths must be written explicitly as `/path/<p>`, as
+    /// [`DisplayOptions::with_explicit_paths`](crate::DisplayOptions::with_explicit_paths)
+    /// does. Lenient mode treats `path` as an ordinary word, like any other path.
+    ///
+    /// This does not check the layering of the stack;
+    /// see [`StackAddr::validate`](crate::StackAddr::validate) for that.
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Recognise the protocols described by `registry`.
+    pub fn with_registry(mut self, registry: &'a ProtocolRegistry) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Check if strict mode is enabled.
+    pub fn strict(&self) -> bool {
+        self.strict
+    }
+
+    /// Returns the registry used to recognise user-defined protocols.
+    pub fn registry(&self) -> Option<&'a ProtocolRegistry> {
+        self.registry
+    }
+}
+
+/// Iterator over the segments of a string form address.
+///
+/// Yields a [`StackAddrError::ParseError`] at the first malformed segment and stops afterwards.
+#[derive(Debug, Clone)]
+pub(crate) struct TextSegments<'a> {
+    input: &'a str,
+    /// Start of the address within `input`, after any leading whitespace.
+    start: usize,
+    /// End of the address within `input`, before any trailing whitespace.
+    end: usize,
+    rest: &'a str,
+    options: ParseOptions<'a>,
+    /// Index of the next segment.
+    index: usize,
+    /// Byte range of the last part taken from the input.
+    last: Range<usize>,
+    failed: bool,
+}
+
+impl<'a> TextSegments<'a> {
+    pub(crate) fn new(s: &'a str, options: ParseOptions<'a>) -> Self {
+        let body = s.trim_end();
+        let rest = body.trim_start();
+        TextSegments {
+            input: s,
+            start: body.len() - rest.len(),
+            end: body.len(),
+            rest,
+            options,
+            index: 0,
+            last: 0..0,
+            failed: false,
+        }
+    }
+
+    /// Returns the next non-empty `/`-separated part.
+    fn next_part(&mut self) -> Option<&'a str> {
+        while !self.rest.is_empty() {
+            let start = self.end - self.rest.len();
+            let (part, tail) = self.rest.split_once('/').unwrap_or((self.rest, ""));
+            self.rest = tail;
+            if !part.is_empty() {
+                self.last = start..start + part.len();
+                return Some(part);
+            }
+        }
+        None
+    }
+
+    /// Returns the next part as the value of the current segment.
+    fn value(&mut self, what: &'static str) -> Result<&'a str, StackAddrError> {
+        self.next_part().ok_or(StackAddrError::MissingPart(what))
+    }
+
+    fn escaped(&mut self, what: &'static str) -> Result<Cow<'a, str>, StackAddrError> {
+        unescape(self.value(what)?)
+    }
+
+    fn port(&mut self, protocol: &str, what: &'static str) -> Result<u16, StackAddrError> {
+        let raw = self.value(what)?;
+        raw.parse()
+            .map_err(|e: std::num::ParseIntError| match e.kind() {
+                IntErrorKind::PosOverflow => StackAddrError::InvalidValue {
+                    protocol: protocol.to_string(),
+                    raw: raw.to_string(),
+                    reason: "port out of range (0-65535)".to_string(),
+                },
+                _ => StackAddrError::InvalidPort {
+                    protocol: protocol.to_string(),
+                    raw: raw.to_string(),
+                },
+            })
+    }
+
+    /// Decodes an identity value, given in base32 or multibase form.
+    fn id(
+        &mut self,
+        what: &'static str,
+        encoding: &'static str,
+    ) -> Result<Cow<'a, [u8]>, StackAddrError> {
+        decode_id(self.value(what)?)
+            .map(Cow::Owned)
+            .ok_or(StackAddrError::InvalidEncoding(encoding))
+    }
+
+    /// Decodes and checks a typed public key.
+    fn key(&mut self, key_type: KeyType) -> Result<SegmentRef<'a>, StackAddrError> {
+        let raw = self.value("public key")?;
+        let invalid = |reason: &str| StackAddrError::InvalidValue {
+            protocol: key_type.name().to_string(),
+            raw: raw.to_string(),
+            reason: reason.to_string(),
+        };
+        let key = decode_id(raw).ok_or_else(|| invalid("invalid base32 or multibase encoding"))?;
+        key_type.check(&key).map_err(invalid)?;
+        Ok(SegmentRef::Identity(IdentityRef::key(
+            key_type,
+            Cow::Owned(key),
+        )))
+    }
+
+    /// Wraps `kind` with the position of the current segment.
+    fn error(
+        &self,
+        kind: StackAddrError,
+        byte_range: Range<usize>,
+        protocol: Option<&str>,
+    ) -> StackAddrError {
+        StackAddrError::ParseError {
+            input: self.input.to_string(),
+            segment_index: self.index,
+            byte_range,
+            protocol: protocol.map(str::to_string),
+            kind: Box::new(kind),
+        }
+    }
+
+    fn segment(&mut self, word: &'a str) -> Result<SegmentRef<'a>, StackAddrError> {
+        let seg = match word {
+            "ip4" => SegmentRef::Protocol(ProtocolRef::Ip4(self.value("ip4 address")?.parse()?)),
+            "ip6" => SegmentRef::Protocol(ProtocolRef::Ip6(self.value("ip6 address")?.parse()?)),
+            "ip6zone" => SegmentRef::Protocol(ProtocolRef::Ip6Zone(self.escaped("ip6 zone")?)),
+            "dns" => SegmentRef::Protocol(ProtocolRef::Dns(self.escaped("dns")?)),
+            "dns4" => SegmentRef::Protocol(ProtocolRef::Dns4(self.escaped("dns4")?)),
+            "dns6" => SegmentRef::Protocol(ProtocolRef::Dns6(self.escaped("dns6")?)),
+            "unix" => SegmentRef::Protocol(ProtocolRef::Unix(unix_path(unescape_bytes(
+                self.value("unix socket path")?,
+            )?)?)),
+            "mac" => {
+                let raw = self.value("mac address")?;
+                let addr = raw.parse().map_err(|_e| StackAddrError::InvalidValue {
+                    protocol: "mac".to_string(),
+                    raw: raw.to_string(),
+                    reason: "invalid MAC address".to_string(),
+                })?;
+                SegmentRef::Protocol(ProtocolRef::Mac(addr))
+            }
+            "tcp" => SegmentRef::Protocol(ProtocolRef::Tcp(self.port("tcp", "tcp port")?)),
+            "udp" => SegmentRef::Protocol(ProtocolRef::Udp(self.port("udp", "udp port")?)),
+            "tls" => SegmentRef::Protocol(ProtocolRef::Tls),
+            "quic" => SegmentRef::Protocol(ProtocolRef::Quic),
+            "http" => SegmentRef::Protocol(ProtocolRef::Http),
+            "https" => SegmentRef::Protocol(ProtocolRef::Https),
+            "ws" => SegmentRef::Protocol(ProtocolRef::Ws(self.port("ws", "ws port")?)),
+            "wss" => SegmentRef::Protocol(ProtocolRef::Wss(self.port("wss", "wss port")?)),
+            "wtr" => SegmentRef::Protocol(ProtocolRef::WebTransport(self.port("wtr", "wtr port")?)),
+            "webrtc" => SegmentRef::Protocol(ProtocolRef::WebRTC),
+            "onion" => SegmentRef::Protocol(ProtocolRef::Onion(self.escaped("onion address")?)),
+            "custom" => SegmentRef::Protocol(ProtocolRef::Custom(self.escaped("custom name")?)),
+            "node" => {
+                SegmentRef::Identity(IdentityRef::NodeId(self.id("node id", "base32 node id")?))
+            }
+            "peer" => {
+                SegmentRef::Identity(IdentityRef::PeerId(self.id("peer id", "base32 peer id")?))
+            }
+            "uuid" => {
+                let raw = self.value("uuid value")?;
+                let uuid =
+                    uuid::Uuid::parse_str(raw).map_err(|_| StackAddrError::InvalidValue {
+                        protocol: "uuid".to_string(),
+                        raw: raw.to_string(),
+                        reason: "invalid UUID".to_string(),
+                    })?;
+                SegmentRef::Identity(IdentityRef::Uuid(uuid))
+            }
+            "identity" => {
+                let kind = self.escaped("identity kind")?;
+                let id = self.id("identity value", "base32 identity")?;
+                SegmentRef::Identity(IdentityRef::Custom { kind, id })
+            }
+            "ed25519" => self.key(KeyType::Ed25519)?,
+            "secp256k1" => self.key(KeyType::Secp256k1)?,
+            "p256" => self.key(KeyType::P256)?,
+            "meta" => {
+                let k = self.escaped("metadata key")?;
+                let v = self.escaped("metadata value")?;
+                SegmentRef::Metadata(k, v)
+            }
+            "path" if self.options.strict => SegmentRef::Path(self.escaped("path")?),
+            word => match self.options.registry.and_then(|r| r.get(word)) {
+                Some(spec) => {
+                    let mut values = Vec::with_capacity(spec.arity());
+                    for _ in 0..spec.arity() {
+                        let value = self.escaped("registered protocol value")?;
+                        spec.validate(&value)?;
+                        values.push(value);
+                    }
+                    SegmentRef::Protocol(ProtocolRef::Registered {
+                        name: Cow::Borrowed(word),
+                        values,
+                    })
+                }
+                None if self.options.strict => {
+                    return Err(StackAddrError::UnknownProtocol(word.to_string()));
+                }
+                None => SegmentRef::Path(unescape(word)?),
+            },
+        };
+        Ok(seg)
+    }
+}
+
+impl<'a> Iterator for TextSegments<'a> {
+    type Item = Result<SegmentRef<'a>, StackAddrError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        if self.start > 0 {
+            self.failed = true;
+            let kind = StackAddrError::UnexpectedLeading(self.input[..self.start].to_string());
+            return Some(Err(self.error(kind, 0..self.start, None)));
+        }
+        let Some(word) = self.next_part() else {
+            if self.end == self.input.len() {
+                return None;
+            }
+            self.failed = true;
+            let kind = StackAddrError::UnexpectedTrailing(self.input[self.end..].to_string());
+            return Some(Err(self.error(kind, self.end..self.input.len(), None)));
+        };
+        let word_range = self.last.clone();
+        let seg = self.segment(word).map_err(|kind| {
+            self.failed = true;
+            // A missing value is reported at the protocol word, anything else at the
+            // part that failed to decode.
+            let byte_range = match kind {
+                StackAddrError::MissingPart(_) => word_range,
+                _ => self.last.clone(),
+            };
+            let known = KEYWORDS.contains(&word)
+                || (self.options.strict && word == "path")
+                || self.options.registry.is_some_and(|r| r.get(word).is_some());
+            self.error(kind, byte_range, known.then_some(word))
+        });
+        self.index += 1;
+        Some(seg)
+    }
+}
@@ -0,0 +1,274 @@
+//! Protocol registry
+//!
+//! Lets applications teach the parser about their own protocols, so that
+//! `/rudp/9000` or `/shm/ring-0` become [`Protocol::Registered`](crate::Protocol::Registered)
+//! segments instead of falling back to paths.
+//!
+//! ```rust
+//! use stackaddr::{Protocol, ProtocolRegistry, ProtocolSpec, StackAddr, StackAddrError};
+//!
+//! let mut registry = ProtocolRegistry::new();
+//! registry
+//!     .register(
+//!         ProtocolSpec::new("rudp", 0x1001)
+//!             .with_arity(1)
+//!             .with_validator(|v| {
+//!                 v.parse::<u16>()
+//!                     .map(|_| ())
+//!                     .map_err(|_| StackAddrError::InvalidEncoding("rudp port"))
+//!             }),
+//!     )
+//!     .unwrap();
+//!
+//! let addr = StackAddr::parse_with(&registry, "/ip4/10.0.0.1/rudp/9000").unwrap();
+//! assert_eq!(
+//!     addr.protocols()[1],
+//!     &Protocol::Registered { name: "rudp".into(), values: vec!["9000".into()] }
+//! );
+//! assert_eq!(addr.to_string(), "/ip4/10.0.0.1/rudp/9000");
+//! ```
+
+use std::{collections::HashMap, fmt, sync::Arc};
+
+use crate::{codec, error::StackAddrError, segment::KEYWORDS};
+
+type Validator = Arc<dyn Fn(&str) -> Result<(), StackAddrError> + Send + Sync>;
+
+/// Description of a user-defined protocol.
+///
+/// A spec has a name (the `/<name>` word in the string form), a binary code,
+/// the number of value components that follow the name, and an optional
+/// validator applied to each value component.
+///
+/// The arity is fixed: every occurrence of the protocol takes exactly that many
+/// values, and a variable number of values is not supported. With an arity of 2,
+/// `/shm/a/b/c` is `shm` with the values `a` and `b` followed by the path `c`, and
+/// `/shm/a` is rejected with [`StackAddrError::MissingPart`].
+#[derive(Clone)]
+pub struct ProtocolSpec {
+    name: String,
+    code: u64,
+    arity: usize,
+    validator: Option<Validator>,
+}
+
+impl ProtocolSpec {
+    /// Create a new spec with no value components.
+    pub fn new(name: impl Into<String>, code: u64) -> Self {
+        ProtocolSpec {
+            name: name.into(),
+            code,
+            arity: 0,
+            validator: None,
+        }
+    }
+
+    /// Set the fixed number of value components that follow the protocol name.
+    pub fn with_arity(mut self, arity: usize) -> Self {
+        self.arity = arity;
+        self
+    }
+
+    /// Set a validator that is applied to every value component on parse.
+    pub fn with_validator<F>(mut self, validator: F) -> Self
+    where
+        F: Fn(&str) -> Result<(), StackAddrError> + Send + Sync + 'static,
+    {
+        self.validator = Some(Arc::new(validator));
+        self
+    }
+
+    /// Returns the protocol name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the binary code of the protocol.
+    pub fn code(&self) -> u64 {
+        self.code
+    }
+
+    /// Returns the number of value components.
+    pub fn arity(&self) -> usize {
+        self.arity
+    }
+
+    /// Validate a single value component.
+    pub fn validate(&self, value: &str) -> Result<(), StackAddrError> {
+        match &self.validator {
+            Some(validator) => validator(value),
+            None => Ok(()),
+        }
+    }
+}
+
+impl fmt::Debug for ProtocolSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProtocolSpec")
+            .field("name", &self.name)
+            .field("code", &self.code)
+            .field("arity", &self.arity)
+            .field("validator", &self.validator.is_some())
+            .finish()
+    }
+}
+
+/// A set of user-defined protocols, indexed by name and by binary code.
+#[derive(Debug, Clone, Default)]
+pub struct ProtocolRegistry {
+    by_name: HashMap<String, ProtocolSpec>,
+    by_code: HashMap<u64, String>,
+}
+
+impl ProtocolRegistry {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a protocol.
+    ///
+    /// Fails when the name is empty, contains `/` or `%`, collides with a built-in
+    /// keyword or an already registered protocol, or when the code is below
+    /// [`codec::FIRST_USER_CODE`], above [`codec::MAX_CODE`] or already taken.
+    pub fn register(&mut self, spec: ProtocolSpec) -> Result<(), StackAddrError> {
+        if spec.name.is_empty() || spec.name.contains(['/', '%']) {
+            return Err(StackAddrError::RegistryConflict(format!(
+                "invalid protocol name {:?}",
+                spec.name
+            )));
+        }
+        if KEYWORDS.contains(&spec.name.as_str()) {
+            return Err(StackAddrError::RegistryConflict(format!(
+                "{} is a built-in protocol",
+                spec.name
+            )));
+        }
+        if self.by_name.contains_key(&spec.name) {
+            return Err(StackAddrError::RegistryConflict(format!(
+                "{} is already registered",
+                spec.name
+            )));
+        }
+        if spec.code < codec::FIRST_USER_CODE {
+            return Err(StackAddrError::RegistryConflict(format!(
+                "code 0x{:x} is reserved",
+                spec.code
+            )));
+        }
+        if spec.code > codec::MAX_CODE {
+            return Err(StackAddrError::RegistryConflict(format!(
+                "code 0x{:x} does not fit in a varint",
+                spec.code
+            )));
+        }
+        if let Some(existing) = self.by_code.get(&spec.code) {
+            return Err(StackAddrError::RegistryConflict(format!(
+                "code 0x{:x} is already used by {}",
+                spec.code, existing
+            )));
+        }
+        self.by_code.insert(spec.code, spec.name.clone());
+        self.by_name.insert(spec.name.clone(), spec);
+        Ok(())
+    }
+
+    /// Look up a protocol by name.
+    pub fn get(&self, name: &str) -> Option<&ProtocolSpec> {
+        self.by_name.get(name)
+    }
+
+    /// Look up a protocol by binary code.
+    pub fn get_by_code(&self, code: u64) -> Option<&ProtocolSpec> {
+        self.by_code
+            .get(&code)
+            .and_then(|name| self.by_name.get(name))
+    }
+
+    /// Returns the number of registered protocols.
+    pub fn len(&self) -> usize {
+        self.by_name.len()
+    }
+
+    /// Check if the registry is empty.
+    pub fn is_empty(&self) -> bool {
+        self.by_name.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_register_and_lookup() {
+        let mut registry = ProtocolRegistry::new();
+        registry
+            .register(ProtocolSpec::new("shm", 0x1002).with_arity(1))
+            .unwrap();
+        assert_eq!(registry.len(), 1);
+        assert_eq!(registry.get("shm").unwrap().code(), 0x1002);
+        assert_eq!(registry.get_by_code(0x1002).unwrap().name(), "shm");
+        assert!(registry.get("rudp").is_none());
+    }
+
+    #[test]
+    fn test_register_conflicts() {
+        let mut registry = ProtocolRegistry::new();
+        registry.register(ProtocolSpec::new("shm", 0x1002)).unwrap();
+
+        for spec in [
+            ProtocolSpec::new("tcp", 0x2000),
+            ProtocolSpec::new("shm", 0x2001),
+            ProtocolSpec::new("other", 0x1002),
+            ProtocolSpec::new("low", codec::TCP),
+            ProtocolSpec::new("", 0x2002),
+            ProtocolSpec::new("a/b", 0x2003),
+            ProtocolSpec::new("huge", codec::MAX_CODE + 1),
+        ] {
+            assert!(matches!(
+                registry.register(spec),
+                Err(StackAddrError::RegistryConflict(_))
+            ));
+        }
+        registry
+            .register(ProtocolSpec::new("max", codec::MAX_CODE))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_fixed_arity() {
+        use crate::{Protocol, Segment, StackAddr};
+
+        let mut registry = ProtocolRegistry::new();
+        registry
+            .register(ProtocolSpec::new("shm", 0x1002).with_arity(2))
+            .unwrap();
+        let addr = StackAddr::parse_with(&registry, "/shm/a/b/c").unwrap();
+        assert_eq!(
+            addr.segments(),
+            &[
+                Segment::Protocol(Protocol::Registered {
+                    name: "shm".into(),
+                    values: vec!["a".into(), "b".into()]
+                }),
+                Segment::Path("c".into()),
+            ]
+        );
+        let err = StackAddr::parse_with(&registry, "/shm/a").unwrap_err();
+        assert!(matches!(err.kind(), StackAddrError::MissingPart(_)));
+    }
+
+    #[test]
+    fn test_validator() {
+        let spec = ProtocolSpec::new("rudp", 0x1001)
+            .with_arity(1)
+            .with_validator(|v| {
+                v.parse::<u16>()
+                    .map(|_| ())
+                    .map_err(|_| StackAddrError::InvalidEncoding("rudp port"))
+            });
+        assert!(spec.validate("9000").is_ok());
+        assert!(spec.validate("nope").is_err());
+    }
+}
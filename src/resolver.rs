@@ -0,0 +1,253 @@
+//! Name resolution
+//!
+//! Pluggable resolvers used by [`StackAddr::resolve_with`](crate::StackAddr::resolve_with)
+//! and [`StackAddr::resolve_with_async`](crate::StackAddr::resolve_with_async) to turn
+//! `/dns`, `/dns4` and `/dns6` segments into concrete `/ip4` and `/ip6` segments.
+//!
+//! - [`Resolver`] is the blocking flavour, [`AsyncResolver`] the async one.
+//!   [`Resolver::scope_id`] also maps `/ip6zone` interface names to IPv6 scope ids.
+//! - [`SystemResolver`] uses the blocking system resolver (`getaddrinfo`).
+//! - [`StaticResolver`] answers from an in-memory table, which is handy for tests
+//!   and for pinning names in configuration.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    future::{self, Future},
+    net::{IpAddr, ToSocketAddrs},
+};
+
+use crate::{error::StackAddrError, segment::protocol::Protocol};
+
+/// The address family requested from a resolver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AddrFamily {
+    /// Both IPv4 and IPv6 addresses (`/dns`)
+    Any,
+    /// IPv4 addresses only (`/dns4`, A records)
+    V4,
+    /// IPv6 addresses only (`/dns6`, AAAA records)
+    V6,
+}
+
+impl AddrFamily {
+    /// Returns the family constraint expressed by a DNS protocol, if `protocol` is one.
+    pub fn of(protocol: &Protocol) -> Option<AddrFamily> {
+        match protocol {
+            Protocol::Dns(_) => Some(AddrFamily::Any),
+            Protocol::Dns4(_) => Some(AddrFamily::V4),
+            Protocol::Dns6(_) => Some(AddrFamily::V6),
+            _ => None,
+        }
+    }
+
+    /// Check if an IP address belongs to this family.
+    pub fn matches(&self, ip: &IpAddr) -> bool {
+        match self {
+            AddrFamily::Any => true,
+            AddrFamily::V4 => ip.is_ipv4(),
+            AddrFamily::V6 => ip.is_ipv6(),
+        }
+    }
+}
+
+impl fmt::Display for AddrFamily {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AddrFamily::Any => write!(f, "any"),
+            AddrFamily::V4 => write!(f, "ipv4"),
+            AddrFamily::V6 => write!(f, "ipv6"),
+        }
+    }
+}
+
+/// A blocking name resolver.
+pub trait Resolver {
+    /// Resolve `name` into IP addresses of the requested family.
+    fn lookup(&self, name: &str, family: AddrFamily) -> Result<Vec<IpAddr>, StackAddrError>;
+
+    /// Resolve an IPv6 zone, usually an interface name such as `eth0`, into its scope id.
+    ///
+    /// Numeric zones are used as they are and never reach the resolver.
+    /// The default implementation looks up the interface on the local system.
+    fn scope_id(&self, zone: &str) -> Result<u32, StackAddrError> {
+        system_scope_id(zone)
+    }
+}
+
+/// Looks up the index of a network interface.
+#[cfg(target_os = "linux")]
+fn system_scope_id(zone: &str) -> Result<u32, StackAddrError> {
+    let unknown = || StackAddrError::ResolutionFailed(format!("unknown interface {}", zone));
+    if zone.is_empty() || zone.contains('/') || zone == "." || zone == ".." {
+        return Err(unknown());
+    }
+    std::fs::read_to_string(format!("/sys/class/net/{}/ifindex", zone))
+        .ok()
+        .and_then(|index| index.trim().parse().ok())
+        .ok_or_else(unknown)
+}
+
+/// Looks up the index of a network interface.
+#[cfg(not(target_os = "linux"))]
+fn system_scope_id(zone: &str) -> Result<u32, StackAddrError> {
+    Err(StackAddrError::ResolutionFailed(format!(
+        "cannot look up interface {} on this platform",
+        zone
+    )))
+}
+
+/// An asynchronous name resolver.
+///
+/// Implement this on top of the DNS client of your runtime to resolve addresses
+/// without blocking it.
+pub trait AsyncResolver {
+    /// Resolve `name` into IP addresses of the requested family.
+    fn lookup(
+        &self,
+        name: &str,
+        family: AddrFamily,
+    ) -> impl Future<Output = Result<Vec<IpAddr>, StackAddrError>> + Send;
+}
+
+/// Resolver backed by the blocking system resolver.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemResolver;
+
+impl Resolver for SystemResolver {
+    fn lookup(&self, name: &str, family: AddrFamily) -> Result<Vec<IpAddr>, StackAddrError> {
+        let addrs = (name, 0)
+            .to_socket_addrs()
+            .map_err(|e| StackAddrError::ResolutionFailed(e.to_string()))?;
+        Ok(addrs
+            .map(|sock| sock.ip())
+            .filter(|ip| family.matches(ip))
+            .collect())
+    }
+}
+
+/// Resolver answering from a fixed in-memory table.
+///
+/// IPv6 zones are answered from a table of their own, see [`StaticResolver::with_zone`].
+#[derive(Debug, Clone, Default)]
+pub struct StaticResolver {
+    entries: HashMap<String, Vec<IpAddr>>,
+    zones: HashMap<String, u32>,
+}
+
+impl StaticResolver {
+    /// Create an empty resolver.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an address for `name`.
+    /// This is a convenience method for creating a resolver with builder pattern.
+    pub fn with(mut self, name: &str, ip: IpAddr) -> Self {
+        self.insert(name, ip);
+        self
+    }
+
+    /// Add an address for `name`.
+    pub fn insert(&mut self, name: &str, ip: IpAddr) {
+        self.entries.entry(name.to_string()).or_default().push(ip);
+    }
+
+    /// Map the IPv6 zone `zone` to `scope_id`.
+    /// This is a convenience method for creating a resolver with builder pattern.
+    pub fn with_zone(mut self, zone: &str, scope_id: u32) -> Self {
+        self.insert_zone(zone, scope_id);
+        self
+    }
+
+    /// Map the IPv6 zone `zone` to `scope_id`.
+    pub fn insert_zone(&mut self, zone: &str, scope_id: u32) {
+        self.zones.insert(zone.to_string(), scope_id);
+    }
+}
+
+impl Resolver for StaticResolver {
+    fn lookup(&self, name: &str, family: AddrFamily) -> Result<Vec<IpAddr>, StackAddrError> {
+        let ips = self
+            .entries
+            .get(name)
+            .ok_or_else(|| StackAddrError::ResolutionFailed(format!("unknown host {}", name)))?;
+        Ok(ips
+            .iter()
+            .copied()
+            .filter(|ip| family.matches(ip))
+            .collect())
+    }
+
+    fn scope_id(&self, zone: &str) -> Result<u32, StackAddrError> {
+        self.zones
+            .get(zone)
+            .copied()
+            .ok_or_else(|| StackAddrError::ResolutionFailed(format!("unknown zone {}", zone)))
+    }
+}
+
+impl AsyncResolver for StaticResolver {
+    fn lookup(
+        &self,
+        name: &str,
+        family: AddrFamily,
+    ) -> impl Future<Output = Result<Vec<IpAddr>, StackAddrError>> + Send {
+        future::ready(Resolver::lookup(self, name, family))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_addr_family() {
+        let v4: IpAddr = "127.0.0.1".parse().unwrap();
+        let v6: IpAddr = "::1".parse().unwrap();
+        assert!(AddrFamily::Any.matches(&v4) && AddrFamily::Any.matches(&v6));
+        assert!(AddrFamily::V4.matches(&v4) && !AddrFamily::V4.matches(&v6));
+        assert!(AddrFamily::V6.matches(&v6) && !AddrFamily::V6.matches(&v4));
+        assert_eq!(
+            AddrFamily::of(&Protocol::Dns4("a".into())),
+            Some(AddrFamily::V4)
+        );
+        assert_eq!(AddrFamily::of(&Protocol::Tcp(80)), None);
+    }
+
+    #[test]
+    fn test_static_resolver_filters_family() {
+        let resolver = StaticResolver::new()
+            .with("example.com", "93.184.216.34".parse().unwrap())
+            .with("example.com", "2606:2800:220:1::".parse().unwrap());
+
+        let v4 = Resolver::lookup(&resolver, "example.com", AddrFamily::V4).unwrap();
+        assert_eq!(v4, vec!["93.184.216.34".parse::<IpAddr>().unwrap()]);
+        let v6 = Resolver::lookup(&resolver, "example.com", AddrFamily::V6).unwrap();
+        assert_eq!(v6, vec!["2606:2800:220:1::".parse::<IpAddr>().unwrap()]);
+        let any = Resolver::lookup(&resolver, "example.com", AddrFamily::Any).unwrap();
+        assert_eq!(any.len(), 2);
+
+        assert!(Resolver::lookup(&resolver, "missing.example", AddrFamily::Any).is_err());
+    }
+
+    #[test]
+    fn test_static_resolver_zones() {
+        let resolver = StaticResolver::new().with_zone("eth0", 2);
+        assert_eq!(resolver.scope_id("eth0").unwrap(), 2);
+        assert!(resolver.scope_id("wlan0").is_err());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_system_scope_id() {
+        assert!(SystemResolver.scope_id("../lo").is_err());
+        assert!(SystemResolver.scope_id("no-such-interface0").is_err());
+    }
+
+    #[test]
+    fn test_system_resolver_localhost() {
+        let ips = SystemResolver.lookup("127.0.0.1", AddrFamily::V4).unwrap();
+        assert_eq!(ips, vec!["127.0.0.1".parse::<IpAddr>().unwrap()]);
+    }
+}
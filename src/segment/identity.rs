@@ -7,12 +7,22 @@
 //!
 //! - `/node/<base32>`: 32-byte NodeId (e.g. Ed25519 public key)
 //! - `/peer/<base32>`: 32-byte PeerId
-//! - `/uuid/<hex>`: Universally Unique Identifier (v1, v4, etc.)
+//! - `/uuid/<hex>`: Universally Unique Identifier (v1, v4, etc.), accepted in simple,
+//!   hyphenated, braced (`{...}`) or URN (`urn:uuid:...`) form
 //! - `/identity/<kind>/<base32>`: Custom identifier with a specified kind
+//! - `/ed25519/<base32>`, `/secp256k1/<base32>`, `/p256/<base32>`: typed public keys
+//!
+//! Typed public keys have their length checked on parse. With the `crypto` feature,
+//! they are also checked to be valid curve points and can verify signatures.
 //!
 //! All binary identity data is encoded using **base32 (RFC4648 without padding)**,
 //! except UUIDs, which are rendered in hyphenless base16 (hex) per convention.
 //!
+//! When parsing, identity values may also be given in [multibase](https://github.com/multiformats/multibase)
+//! form, e.g. `/peer/z6Mk...` (base58btc) or `/node/b...` (lowercase base32). See [`Multibase`].
+//! A value that is itself canonical base32 is always read as such, so upper-case multibase
+//! values (`B...`, `V...`, `F...`) are only recognised when they are not.
+//!
 //! Identity segments are useful for describing endpoints in a cryptographically verifiable or
 //! globally unique way.
 
@@ -23,8 +33,73 @@ use uuid::Uuid;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+use super::escape;
 use crate::StackAddrError;
 
+/// Multibase encodings supported for identity values.
+///
+/// Encoded strings carry a one-character prefix naming their base, so they can be
+/// decoded without knowing the base in advance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Multibase {
+    /// RFC4648 base32, lowercase, no padding (`b`)
+    Base32,
+    /// RFC4648 base32hex, lowercase, no padding (`v`)
+    Base32Hex,
+    /// Bitcoin base58 (`z`)
+    Base58Btc,
+    /// RFC4648 URL-safe base64, no padding (`u`)
+    Base64Url,
+    /// Lowercase hexadecimal (`f`)
+    Hex,
+}
+
+impl Multibase {
+    /// Returns the multibase prefix character.
+    pub fn prefix(&self) -> char {
+        self.base().code()
+    }
+
+    fn base(&self) -> multibase::Base {
+        match self {
+            Multibase::Base32 => multibase::Base::Base32Lower,
+            Multibase::Base32Hex => multibase::Base::Base32HexLower,
+            Multibase::Base58Btc => multibase::Base::Base58Btc,
+            Multibase::Base64Url => multibase::Base::Base64Url,
+            Multibase::Hex => multibase::Base::Base16Lower,
+        }
+    }
+
+    /// Maps a decoded base to a supported one, accepting upper-case variants.
+    fn from_base(base: multibase::Base) -> Option<Multibase> {
+        use multibase::Base;
+        match base {
+            Base::Base32Lower | Base::Base32Upper => Some(Multibase::Base32),
+            Base::Base32HexLower | Base::Base32HexUpper => Some(Multibase::Base32Hex),
+            Base::Base58Btc => Some(Multibase::Base58Btc),
+            Base::Base64Url => Some(Multibase::Base64Url),
+            Base::Base16Lower | Base::Base16Upper => Some(Multibase::Hex),
+            _ => None,
+        }
+    }
+}
+
+/// Decodes an identity value from the string form.
+///
+/// Lower-case multibase prefixes never occur in canonical (upper-case) base32, so such
+/// values are decoded as multibase. Otherwise the value is read as canonical base32 if
+/// it is exactly what [`Identity`]'s `Display` would write, and as multibase if not.
+pub(crate) fn decode_id(encoded: &str) -> Option<Vec<u8>> {
+    let alphabet = base32::Alphabet::Rfc4648 { padding: false };
+    let multibase = || Identity::decode_multibase(encoded).ok().map(Vec::from);
+    if encoded.starts_with(['b', 'v', 'z', 'u', 'f']) {
+        return multibase();
+    }
+    base32::decode(alphabet, encoded)
+        .filter(|decoded| base32::encode(alphabet, decoded) == encoded)
+        .or_else(multibase)
+}
+
 /// A segment representing a unique identity.
 ///
 /// This can be used to include cryptographic identities (like NodeId, PeerId),
@@ -42,6 +117,131 @@ pub enum Identity {
     Uuid(Uuid),
     /// A custom identity with an explicit kind and ID bytes
     Custom { kind: String, id: Bytes },
+    /// An Ed25519 public key (32 bytes)
+    Ed25519(Bytes),
+    /// A secp256k1 public key (33-byte compressed SEC1)
+    Secp256k1(Bytes),
+    /// A NIST P-256 public key (33-byte compressed SEC1)
+    P256(Bytes),
+}
+
+/// An [`Identity`] normalised for comparison and hashing.
+///
+/// Identities carrying the same key bytes map to the same `IdentityKey`, whether they
+/// were written as `/node/X`, `/peer/X` or `/identity/<kind>/X`. Typed keys keep their
+/// [`KeyType`], since equal bytes on different curves are different keys, and UUIDs
+/// stay distinct from keys, even when their 16 bytes happen to match.
+///
+/// ```rust
+/// use std::collections::HashMap;
+/// use bytes::Bytes;
+/// use stackaddr::{Identity, IdentityKey};
+///
+/// let key = Bytes::from_static(&[7; 32]);
+/// let mut seen = HashMap::new();
+/// seen.insert(IdentityKey::from(&Identity::NodeId(key.clone())), "node");
+/// assert_eq!(seen.get(&Identity::PeerId(key).key()), Some(&"node"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct IdentityKey(KeyInner);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum KeyInner {
+    Key(Bytes),
+    Typed(KeyType, Bytes),
+    Uuid(Uuid),
+}
+
+impl IdentityKey {
+    /// Returns the normalised identity bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        match &self.0 {
+            KeyInner::Key(b) | KeyInner::Typed(_, b) => b,
+            KeyInner::Uuid(u) => u.as_bytes(),
+        }
+    }
+
+    /// Returns the key type if this key came from a typed key identity.
+    pub fn key_type(&self) -> Option<KeyType> {
+        match self.0 {
+            KeyInner::Typed(key_type, _) => Some(key_type),
+            _ => None,
+        }
+    }
+
+    /// Check if this key came from a UUID identity.
+    pub fn is_uuid(&self) -> bool {
+        matches!(self.0, KeyInner::Uuid(_))
+    }
+}
+
+impl From<&Identity> for IdentityKey {
+    fn from(identity: &Identity) -> Self {
+        match identity {
+            Identity::Uuid(u) => IdentityKey(KeyInner::Uuid(*u)),
+            Identity::NodeId(b) | Identity::PeerId(b) | Identity::Custom { id: b, .. } => {
+                IdentityKey(KeyInner::Key(b.clone()))
+            }
+            Identity::Ed25519(b) => IdentityKey(KeyInner::Typed(KeyType::Ed25519, b.clone())),
+            Identity::Secp256k1(b) => IdentityKey(KeyInner::Typed(KeyType::Secp256k1, b.clone())),
+            Identity::P256(b) => IdentityKey(KeyInner::Typed(KeyType::P256, b.clone())),
+        }
+    }
+}
+
+/// The public key algorithm of a typed key identity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum KeyType {
+    /// Ed25519
+    Ed25519,
+    /// secp256k1 ECDSA
+    Secp256k1,
+    /// NIST P-256 ECDSA
+    P256,
+}
+
+impl KeyType {
+    /// Returns the protocol name used in the string form, e.g. `ed25519`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            KeyType::Ed25519 => "ed25519",
+            KeyType::Secp256k1 => "secp256k1",
+            KeyType::P256 => "p256",
+        }
+    }
+
+    /// Returns the length of an encoded public key.
+    pub fn key_len(&self) -> usize {
+        match self {
+            KeyType::Ed25519 => 32,
+            KeyType::Secp256k1 | KeyType::P256 => 33,
+        }
+    }
+
+    /// Returns the multicodec code of the public key type, e.g. `0xed` for `ed25519-pub`.
+    pub fn multicodec(&self) -> u64 {
+        match self {
+            KeyType::Ed25519 => 0xed,
+            KeyType::Secp256k1 => 0xe7,
+            KeyType::P256 => 0x1200,
+        }
+    }
+
+    /// Check that `key` is a well-formed public key of this type.
+    ///
+    /// Only the length is checked, unless the `crypto` feature is enabled, in which
+    /// case the key must also decode to a valid curve point.
+    pub fn check(&self, key: &[u8]) -> Result<(), &'static str> {
+        if key.len() != self.key_len() {
+            return Err(match self {
+                KeyType::Ed25519 => "expected 32 bytes",
+                KeyType::Secp256k1 | KeyType::P256 => "expected 33 bytes",
+            });
+        }
+        #[cfg(feature = "crypto")]
+        crate::crypto::check_point(*self, key)?;
+        Ok(())
+    }
 }
 
 impl Identity {
@@ -53,6 +253,30 @@ impl Identity {
             Identity::NodeId(b) | Identity::PeerId(b) => b,
             Identity::Custom { id, .. } => id,
             Identity::Uuid(u) => u.as_bytes(),
+            Identity::Ed25519(k) | Identity::Secp256k1(k) | Identity::P256(k) => k,
+        }
+    }
+
+    /// Returns the normalised form of this identity, see [`IdentityKey`].
+    pub fn key(&self) -> IdentityKey {
+        IdentityKey::from(self)
+    }
+
+    /// Check if both identities refer to the same key, regardless of how they are written.
+    ///
+    /// `/node/X`, `/peer/X` and `/identity/<kind>/X` are the same key; a typed key only
+    /// matches a key of the same type, and a UUID only matches the same UUID.
+    pub fn same_key(&self, other: &Identity) -> bool {
+        self.key() == other.key()
+    }
+
+    /// Returns the key type of a typed public key identity.
+    pub fn key_type(&self) -> Option<KeyType> {
+        match self {
+            Identity::Ed25519(_) => Some(KeyType::Ed25519),
+            Identity::Secp256k1(_) => Some(KeyType::Secp256k1),
+            Identity::P256(_) => Some(KeyType::P256),
+            _ => None,
         }
     }
 
@@ -70,6 +294,21 @@ impl Identity {
         base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(self.id_bytes())
     }
 
+    /// Encodes the identity bytes as a multibase string, e.g. `z...` for base58btc.
+    pub fn encode(&self, base: Multibase) -> String {
+        multibase::encode(base.base(), self.id_bytes())
+    }
+
+    /// Decodes a multibase string into identity bytes.
+    ///
+    /// Only the bases listed in [`Multibase`] (and their upper-case variants) are accepted.
+    pub fn decode_multibase(encoded: &str) -> Result<Bytes, StackAddrError> {
+        let (base, decoded) =
+            multibase::decode(encoded).map_err(|_| StackAddrError::InvalidEncoding("multibase"))?;
+        Multibase::from_base(base).ok_or(StackAddrError::InvalidEncoding("multibase base"))?;
+        Ok(Bytes::from(decoded))
+    }
+
     pub fn from_base32_node(encoded: &str) -> Result<Self, StackAddrError> {
         let decoded = base32::decode(base32::Alphabet::Rfc4648 { padding: false }, encoded)
             .ok_or(StackAddrError::InvalidEncoding("base32 node id"))?;
@@ -95,6 +334,62 @@ impl Identity {
     }
 }
 
+/// Multihash code of the identity hash, which stores its input unchanged.
+#[cfg(feature = "multihash")]
+pub const IDENTITY_HASH: u64 = 0x00;
+
+#[cfg(feature = "multihash")]
+impl Identity {
+    /// Wraps the identity bytes in an identity multihash (`multihash` feature).
+    ///
+    /// Typed public keys are prefixed with the varint [multicodec](KeyType::multicodec)
+    /// of their key type, so the key type is self-describing and
+    /// [`Identity::from_multihash`] can restore it. Other identities are wrapped as they
+    /// are. Fails for ids longer than 64 bytes.
+    pub fn to_multihash(&self) -> Result<multihash::Multihash<64>, StackAddrError> {
+        let mut digest = bytes::BytesMut::new();
+        if let Some(key_type) = self.key_type() {
+            crate::codec::put_varint(&mut digest, key_type.multicodec());
+        }
+        digest.extend_from_slice(self.id_bytes());
+        multihash::Multihash::wrap(IDENTITY_HASH, &digest)
+            .map_err(|_| StackAddrError::InvalidEncoding("multihash"))
+    }
+
+    /// Restores a typed public key from [`Identity::to_multihash`] (`multihash` feature).
+    ///
+    /// The multihash must be an identity multihash holding a multicodec-prefixed key.
+    pub fn from_multihash(mh: &multihash::Multihash<64>) -> Result<Identity, StackAddrError> {
+        if mh.code() != IDENTITY_HASH {
+            return Err(StackAddrError::InvalidEncoding("identity multihash"));
+        }
+        let mut digest = mh.digest();
+        let code = crate::codec::get_varint(&mut digest)?;
+        let key_type = [KeyType::Ed25519, KeyType::Secp256k1, KeyType::P256]
+            .into_iter()
+            .find(|k| k.multicodec() == code)
+            .ok_or(StackAddrError::InvalidEncoding("public key multicodec"))?;
+        key_type
+            .check(digest)
+            .map_err(|_| StackAddrError::InvalidEncoding("invalid public key"))?;
+        let key = Bytes::copy_from_slice(digest);
+        Ok(match key_type {
+            KeyType::Ed25519 => Identity::Ed25519(key),
+            KeyType::Secp256k1 => Identity::Secp256k1(key),
+            KeyType::P256 => Identity::P256(key),
+        })
+    }
+
+    /// Reads the identity bytes as a multihash (`multihash` feature).
+    ///
+    /// This is the inverse of storing `to_multihash().to_bytes()` as the id, and also
+    /// reads libp2p peer ids, which are multihashes.
+    pub fn as_multihash(&self) -> Result<multihash::Multihash<64>, StackAddrError> {
+        multihash::Multihash::from_bytes(self.id_bytes())
+            .map_err(|_| StackAddrError::InvalidEncoding("multihash"))
+    }
+}
+
 impl fmt::Display for Identity {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         use Identity::*;
@@ -110,7 +405,12 @@ impl fmt::Display for Identity {
             Uuid(uuid) => write!(f, "/uuid/{}", uuid.simple()),
             Custom { kind, id } => {
                 let encoded = base32::encode(base32::Alphabet::Rfc4648 { padding: false }, id);
-                write!(f, "/identity/{}/{}", kind, encoded)
+                write!(f, "/identity/{}/{}", escape(kind), encoded)
+            }
+            Ed25519(key) | Secp256k1(key) | P256(key) => {
+                let encoded = base32::encode(base32::Alphabet::Rfc4648 { padding: false }, key);
+                let key_type = self.key_type().expect("typed key");
+                write!(f, "/{}/{}", key_type.name(), encoded)
             }
         }
     }
@@ -141,7 +441,7 @@ mod tests {
         let encoded = base32::encode(base32::Alphabet::Rfc4648 { padding: false }, &id);
         let proto = Identity::Custom {
             kind: "some-p2p".to_string(),
-            id: id,
+            id,
         };
         assert_eq!(proto.to_string(), format!("/identity/some-p2p/{}", encoded));
     }
@@ -243,4 +543,135 @@ mod tests {
         assert!(Identity::from_base32_peer(invalid).is_err());
         assert!(Identity::from_base32_custom("kind", invalid).is_err());
     }
+
+    #[test]
+    fn test_multibase_roundtrip() {
+        let identity = Identity::NodeId(random_bytes32());
+        for base in [
+            Multibase::Base32,
+            Multibase::Base32Hex,
+            Multibase::Base58Btc,
+            Multibase::Base64Url,
+            Multibase::Hex,
+        ] {
+            let encoded = identity.encode(base);
+            assert!(encoded.starts_with(base.prefix()));
+            assert_eq!(
+                Identity::decode_multibase(&encoded).unwrap(),
+                identity.id_bytes()
+            );
+        }
+        assert_eq!(
+            Identity::NodeId(Bytes::from_static(b"hello")).encode(Multibase::Base58Btc),
+            "zCn8eVZg"
+        );
+        // Base36 is valid multibase but not supported here.
+        assert!(Identity::decode_multibase("k2lsnrkf3j").is_err());
+    }
+
+    #[test]
+    fn test_decode_id_prefers_plain_base32() {
+        // "BAAA" is valid plain base32 and would also look like base32upper multibase.
+        assert_eq!(decode_id("BAAA").unwrap(), [0x08, 0x00]);
+        assert_eq!(decode_id("zCn8eVZg").unwrap(), b"hello");
+        assert!(decode_id("not-an-id").is_none());
+    }
+
+    #[test]
+    fn test_decode_id_upper_case_multibase() {
+        let id = [0xde, 0xad, 0xbe, 0xef, 0x01];
+        for base in [
+            multibase::Base::Base32Upper,
+            multibase::Base::Base32HexUpper,
+            multibase::Base::Base16Upper,
+        ] {
+            let encoded = multibase::encode(base, id);
+            assert_eq!(decode_id(&encoded).unwrap(), id, "{}", encoded);
+            assert_eq!(Identity::decode_multibase(&encoded).unwrap(), id[..]);
+        }
+
+        // Canonical ids starting with an upper-case multibase prefix stay base32.
+        for first in [0x08, 0xa8, 0x28] {
+            let mut id = random_bytes32().to_vec();
+            id[0] = first;
+            let identity = Identity::NodeId(Bytes::from(id));
+            let s = identity.to_string();
+            assert!(
+                s.starts_with("/node/B") || s.starts_with("/node/V") || s.starts_with("/node/F")
+            );
+            let parsed: crate::StackAddr = s.parse().unwrap();
+            assert_eq!(parsed.get_identity(), Some(&identity));
+        }
+    }
+
+    #[test]
+    fn test_same_key() {
+        let id = random_bytes32();
+        let node = Identity::NodeId(id.clone());
+        let peer = Identity::PeerId(id.clone());
+        let custom = Identity::Custom {
+            kind: "myproto".into(),
+            id: id.clone(),
+        };
+        assert_ne!(node, peer);
+        assert!(node.same_key(&peer) && peer.same_key(&custom));
+        assert_eq!(node.key(), custom.key());
+        assert!(!node.same_key(&Identity::NodeId(random_bytes32())));
+
+        // A UUID never matches a key, even with the same bytes.
+        let uuid = Uuid::from_bytes([1; 16]);
+        let raw = Identity::NodeId(Bytes::copy_from_slice(uuid.as_bytes()));
+        assert!(!Identity::Uuid(uuid).same_key(&raw));
+        assert!(Identity::Uuid(uuid).key().is_uuid());
+        assert_eq!(raw.key().as_bytes(), uuid.as_bytes());
+
+        // The same SEC1 bytes on different curves are different keys.
+        let sec1 = Bytes::from(vec![2; 33]);
+        let k256 = Identity::Secp256k1(sec1.clone());
+        let p256 = Identity::P256(sec1.clone());
+        assert!(!k256.same_key(&p256));
+        assert_ne!(k256.key(), p256.key());
+        assert!(!k256.same_key(&Identity::NodeId(sec1)));
+        assert!(k256.same_key(&k256.clone()));
+        assert_eq!(p256.key().key_type(), Some(KeyType::P256));
+    }
+
+    #[cfg(feature = "multihash")]
+    #[test]
+    fn test_multihash_wrap() {
+        let identity = Identity::NodeId(random_bytes32());
+        let mh = identity.to_multihash().unwrap();
+        assert_eq!(mh.code(), IDENTITY_HASH);
+        assert_eq!(mh.digest(), identity.id_bytes());
+
+        let wrapped = Identity::PeerId(Bytes::from(mh.to_bytes()));
+        assert_eq!(wrapped.as_multihash().unwrap(), mh);
+        assert!(identity.as_multihash().is_err());
+        assert!(Identity::from_multihash(&mh).is_err());
+    }
+
+    #[cfg(feature = "multihash")]
+    #[test]
+    fn test_multihash_typed_key() {
+        // Compressed P-256 generator and the Ed25519 base point.
+        let hex = |s: &str| -> Bytes {
+            (0..s.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+                .collect()
+        };
+        let p256 = Identity::P256(hex(
+            "026b17d1f2e12c4247f8bce6e563a440f277037d812deb33a0f4a13945d898c296",
+        ));
+        let mh = p256.to_multihash().unwrap();
+        assert_eq!(&mh.digest()[..3], &[0x80, 0x24, 0x02]);
+        assert_eq!(Identity::from_multihash(&mh).unwrap(), p256);
+
+        let ed25519 = Identity::Ed25519(hex(
+            "5866666666666666666666666666666666666666666666666666666666666666",
+        ));
+        let mh = ed25519.to_multihash().unwrap();
+        assert_eq!(&mh.digest()[..2], &[0xed, 0x01]);
+        assert_eq!(Identity::from_multihash(&mh).unwrap(), ed25519);
+    }
 }
@@ -3,7 +3,9 @@ pub mod protocol;
 
 use identity::Identity;
 use protocol::Protocol;
-use std::fmt;
+use std::{borrow::Cow, fmt};
+
+use crate::StackAddrError;
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
@@ -15,6 +17,9 @@ use serde::{Deserialize, Serialize};
 /// - cryptographic identity layers
 /// - metadata annotations
 /// - resource paths
+///
+/// Free-form values (paths, metadata, names) are percent-escaped in their string form,
+/// so values containing `/` or `%`, or empty values, survive a `Display`/`FromStr` round-trip.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Segment {
@@ -33,8 +38,208 @@ impl fmt::Display for Segment {
         match self {
             Segment::Protocol(p) => write!(f, "{}", p),
             Segment::Identity(i) => write!(f, "{}", i),
-            Segment::Path(p) => write!(f, "/{}", p),
-            Segment::Metadata(k, v) => write!(f, "/meta/{}/{}", k, v),
+            Segment::Path(p) => write!(f, "/{}", escape_path(p)),
+            Segment::Metadata(k, v) => write!(f, "/meta/{}/{}", escape(k), escape(v)),
+        }
+    }
+}
+
+/// Words that the parser treats as the start of a protocol, identity or metadata segment.
+///
+/// A path segment equal to one of these is escaped so that it re-parses as a path.
+pub(crate) const KEYWORDS: &[&str] = &[
+    "ip4",
+    "ip6",
+    "ip6zone",
+    "dns",
+    "dns4",
+    "dns6",
+    "unix",
+    "mac",
+    "tcp",
+    "udp",
+    "tls",
+    "quic",
+    "http",
+    "https",
+    "ws",
+    "wss",
+    "wtr",
+    "webrtc",
+    "onion",
+    "custom",
+    "node",
+    "peer",
+    "uuid",
+    "identity",
+    "ed25519",
+    "secp256k1",
+    "p256",
+    "meta",
+];
+
+/// Escapes a free-form value so that it occupies exactly one `/`-separated part.
+///
+/// `%` and `/` are percent-encoded (`%25`, `%2F`), as are whitespace and control characters
+/// so that the string form never contains any. An empty value is written as a bare `%`.
+pub(crate) fn escape(value: &str) -> Cow<'_, str> {
+    if value.is_empty() {
+        return Cow::Borrowed("%");
+    }
+    if !value.contains(|c: char| c == '%' || c == '/' || c.is_whitespace() || c.is_control()) {
+        return Cow::Borrowed(value);
+    }
+    let mut out = String::with_capacity(value.len() + 4);
+    for c in value.chars() {
+        match c {
+            '%' => out.push_str("%25"),
+            '/' => out.push_str("%2F"),
+            c if c.is_whitespace() || c.is_control() => {
+                for b in c.encode_utf8(&mut [0; 4]).bytes() {
+                    out.push_str(&format!("%{:02X}", b));
+                }
+            }
+            c => out.push(c),
+        }
+    }
+    Cow::Owned(out)
+}
+
+/// Escapes raw bytes like [`escape`], percent-encoding bytes that are not valid UTF-8.
+pub(crate) fn escape_bytes(value: &[u8]) -> Cow<'_, str> {
+    if let Ok(value) = std::str::from_utf8(value) {
+        return escape(value);
+    }
+    let mut out = String::with_capacity(value.len() + 8);
+    for chunk in value.utf8_chunks() {
+        if !chunk.valid().is_empty() {
+            out.push_str(&escape(chunk.valid()));
+        }
+        for b in chunk.invalid() {
+            out.push_str(&format!("%{:02X}", b));
         }
     }
+    Cow::Owned(out)
+}
+
+/// Escapes a path value, additionally escaping values that collide with a parser keyword.
+pub(crate) fn escape_path(value: &str) -> Cow<'_, str> {
+    if KEYWORDS.contains(&value) {
+        return escape_leading(value);
+    }
+    escape(value)
+}
+
+/// Escapes a value with its first character always percent-encoded, so that it does not
+/// read as a protocol word.
+pub(crate) fn escape_leading(value: &str) -> Cow<'_, str> {
+    let mut chars = value.chars();
+    let Some(first) = chars.next() else {
+        return escape(value);
+    };
+    let mut out = String::with_capacity(value.len() + 4);
+    for b in first.encode_utf8(&mut [0; 4]).bytes() {
+        out.push_str(&format!("%{:02X}", b));
+    }
+    if !chars.as_str().is_empty() {
+        out.push_str(&escape(chars.as_str()));
+    }
+    Cow::Owned(out)
+}
+
+/// Reverses [`escape`], decoding `%XX` sequences and the bare `%` empty marker.
+pub(crate) fn unescape(part: &str) -> Result<Cow<'_, str>, StackAddrError> {
+    match unescape_bytes(part)? {
+        Cow::Borrowed(_) if part == "%" => Ok(Cow::Borrowed("")),
+        Cow::Borrowed(_) => Ok(Cow::Borrowed(part)),
+        Cow::Owned(out) => String::from_utf8(out)
+            .map(Cow::Owned)
+            .map_err(|_| StackAddrError::InvalidEncoding("percent escape")),
+    }
+}
+
+/// Reverses [`escape_bytes`], allowing escapes that decode to invalid UTF-8.
+pub(crate) fn unescape_bytes(part: &str) -> Result<Cow<'_, [u8]>, StackAddrError> {
+    if part == "%" {
+        return Ok(Cow::Borrowed(&[]));
+    }
+    if !part.contains('%') {
+        return Ok(Cow::Borrowed(part.as_bytes()));
+    }
+    let bytes = part.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = bytes
+                .get(i + 1..i + 3)
+                .and_then(|h| std::str::from_utf8(h).ok())
+                .and_then(|h| u8::from_str_radix(h, 16).ok())
+                .ok_or(StackAddrError::InvalidEncoding("percent escape"))?;
+            out.push(hex);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    Ok(Cow::Owned(out))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_roundtrip() {
+        for value in [
+            "",
+            "plain",
+            "a/b",
+            "100%",
+            "%2F",
+            "/",
+            "%",
+            "dir/file%20name",
+        ] {
+            let escaped = escape(value);
+            assert!(!escaped.contains('/'));
+            assert!(!escaped.is_empty());
+            assert_eq!(unescape(&escaped).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn test_escape_whitespace() {
+        assert_eq!(escape("a b\n"), "a%20b%0A");
+        assert_eq!(unescape("a%20b%0A").unwrap(), "a b\n");
+        assert_eq!(escape("\0abstract"), "%00abstract");
+    }
+
+    #[test]
+    fn test_escape_path_keyword() {
+        assert_eq!(escape_path("tcp"), "%74cp");
+        assert_eq!(unescape(&escape_path("tcp")).unwrap(), "tcp");
+        assert_eq!(escape_path("images"), "images");
+        assert_eq!(escape_leading("a"), "%61");
+        assert_eq!(escape_leading("é b"), "%C3%A9%20b");
+        assert_eq!(unescape(&escape_leading("é b")).unwrap(), "é b");
+    }
+
+    #[test]
+    fn test_escape_bytes_roundtrip() {
+        for value in [&b"/tmp/a\xffb.sock"[..], b"\xc3", b"", b"plain", b"\0x"] {
+            let escaped = escape_bytes(value);
+            assert!(!escaped.contains('/'));
+            assert_eq!(unescape_bytes(&escaped).unwrap(), value);
+        }
+        assert_eq!(escape_bytes(b"a\xffb"), "a%FFb");
+    }
+
+    #[test]
+    fn test_unescape_invalid() {
+        assert!(unescape("%zz").is_err());
+        assert!(unescape("abc%2").is_err());
+        assert!(unescape("%ff").is_err());
+    }
 }
@@ -4,7 +4,7 @@
 //! used in layered network addressing. These include:
 //!
 //! - **Link-layer**: e.g. `/mac/aa:bb:cc:dd:ee:ff`
-//! - **Network-layer**: e.g. `/ip4/`, `/ip6/`
+//! - **Network-layer**: e.g. `/ip4/`, `/ip6/`, `/ip6zone/eth0/ip6/fe80::1`, `/unix/%2Frun%2Fapp.sock`
 //! - **Transport-layer**: e.g. `/tcp/`, `/udp/`, `/tls/`, `/quic/`
 //! - **Application-layer**: e.g. `/http/`, `/wtr/`, `/webrtc/`, `/onion/...`
 //!
@@ -13,10 +13,14 @@
 //!
 //! This enum is designed for composability within a [`StackAddr`](crate::StackAddr).
 
+use super::{escape, escape_bytes};
+use crate::borrowed::unix_path_bytes;
+use crate::codec;
 use mac_addr::MacAddr;
 use std::{
     fmt,
     net::{Ipv4Addr, Ipv6Addr},
+    path::PathBuf,
 };
 
 #[cfg(feature = "serde")]
@@ -35,12 +39,20 @@ pub enum Protocol {
     Ip4(Ipv4Addr),
     /// IPv6 address (layer 3)
     Ip6(Ipv6Addr),
+    /// IPv6 zone (scope) of the following `/ip6`, as an interface name or a numeric scope id
+    Ip6Zone(String),
     /// DNS (unspecified family)
     Dns(String),
     /// DNS (IPv4)
     Dns4(String),
     /// DNS (IPv6)
     Dns6(String),
+    /// Unix domain socket path
+    ///
+    /// A path starting with a NUL byte (written `%00`) names a socket in the Linux
+    /// abstract namespace. The string and binary forms keep the raw path bytes, with
+    /// bytes that are not UTF-8 percent-escaped in the string form.
+    Unix(PathBuf),
     /// TCP port (layer 4)
     Tcp(u16),
     /// UDP port (layer 4)
@@ -65,6 +77,60 @@ pub enum Protocol {
     Onion(String),
     /// Arbitrary custom protocol
     Custom(String),
+    /// A user-defined protocol described by a [`ProtocolRegistry`](crate::ProtocolRegistry)
+    Registered { name: String, values: Vec<String> },
+}
+
+impl Protocol {
+    /// Returns the network layer this protocol belongs to.
+    ///
+    /// Returns `None` for [`Protocol::Custom`] and [`Protocol::Registered`],
+    /// whose layer is not known to this crate.
+    pub fn layer(&self) -> Option<Layer> {
+        use Protocol::*;
+        match self {
+            Mac(_) => Some(Layer::Link),
+            Ip4(_) | Ip6(_) | Ip6Zone(_) | Dns(_) | Dns4(_) | Dns6(_) | Unix(_) => {
+                Some(Layer::Network)
+            }
+            Tcp(_) | Udp(_) | Tls | Quic => Some(Layer::Transport),
+            Http | Https | Ws(_) | Wss(_) | WebTransport(_) | WebRTC | Onion(_) => {
+                Some(Layer::Application)
+            }
+            Custom(_) | Registered { .. } => None,
+        }
+    }
+
+    /// Returns the binary code of this protocol, as used by [`codec`](crate::codec).
+    ///
+    /// [`Protocol::Registered`] returns the generic [`codec::REGISTERED`] code,
+    /// since its registered code is only known to a [`ProtocolRegistry`](crate::ProtocolRegistry).
+    pub fn code(&self) -> u64 {
+        use Protocol::*;
+        match self {
+            Mac(_) => codec::MAC,
+            Ip4(_) => codec::IP4,
+            Ip6(_) => codec::IP6,
+            Ip6Zone(_) => codec::IP6ZONE,
+            Dns(_) => codec::DNS,
+            Dns4(_) => codec::DNS4,
+            Dns6(_) => codec::DNS6,
+            Unix(_) => codec::UNIX,
+            Tcp(_) => codec::TCP,
+            Udp(_) => codec::UDP,
+            Tls => codec::TLS,
+            Quic => codec::QUIC,
+            Http => codec::HTTP,
+            Https => codec::HTTPS,
+            Ws(_) => codec::WS,
+            Wss(_) => codec::WSS,
+            WebTransport(_) => codec::WEBTRANSPORT,
+            WebRTC => codec::WEBRTC,
+            Onion(_) => codec::ONION,
+            Custom(_) => codec::CUSTOM,
+            Registered { .. } => codec::REGISTERED,
+        }
+    }
 }
 
 impl fmt::Display for Protocol {
@@ -73,9 +139,11 @@ impl fmt::Display for Protocol {
         match self {
             Ip4(addr) => write!(f, "/ip4/{}", addr),
             Ip6(addr) => write!(f, "/ip6/{}", addr),
-            Dns(name) => write!(f, "/dns/{}", name),
-            Dns4(name) => write!(f, "/dns4/{}", name),
-            Dns6(name) => write!(f, "/dns6/{}", name),
+            Ip6Zone(zone) => write!(f, "/ip6zone/{}", escape(zone)),
+            Dns(name) => write!(f, "/dns/{}", escape(name)),
+            Dns4(name) => write!(f, "/dns4/{}", escape(name)),
+            Dns6(name) => write!(f, "/dns6/{}", escape(name)),
+            Unix(path) => write!(f, "/unix/{}", escape_bytes(unix_path_bytes(path))),
             Mac(addr) => write!(f, "/mac/{}", addr),
             Tcp(port) => write!(f, "/tcp/{}", port),
             Udp(port) => write!(f, "/udp/{}", port),
@@ -87,12 +155,33 @@ impl fmt::Display for Protocol {
             Wss(port) => write!(f, "/wss/{}", port),
             WebTransport(port) => write!(f, "/wtr/{}", port),
             WebRTC => write!(f, "/webrtc"),
-            Onion(addr) => write!(f, "/onion/{}", addr),
-            Custom(name) => write!(f, "/custom/{}", name),
+            Onion(addr) => write!(f, "/onion/{}", escape(addr)),
+            Custom(name) => write!(f, "/custom/{}", escape(name)),
+            Registered { name, values } => {
+                write!(f, "/{}", name)?;
+                for value in values {
+                    write!(f, "/{}", escape(value))?;
+                }
+                Ok(())
+            }
         }
     }
 }
 
+/// The layer of a [`Protocol`], ordered from the bottom of the stack to the top.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Layer {
+    /// Link layer (L2), e.g. `/mac`
+    Link,
+    /// Network layer (L3), e.g. `/ip4`, `/ip6`, `/dns`
+    Network,
+    /// Transport layer (L4), e.g. `/tcp`, `/udp`, `/tls`, `/quic`
+    Transport,
+    /// Application layer (L7), e.g. `/http`, `/ws`, `/wtr`, `/onion`
+    Application,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum TransportProtocol {
@@ -125,6 +214,33 @@ impl TransportProtocol {
             | TransportProtocol::WebTransport(p) => *p,
         }
     }
+    /// Returns the kind of transport, without its port.
+    pub fn kind(&self) -> TransportKind {
+        match self {
+            TransportProtocol::Tcp(_) => TransportKind::Tcp,
+            TransportProtocol::Udp(_) => TransportKind::Udp,
+            TransportProtocol::TlsTcp(_) => TransportKind::TlsTcp,
+            TransportProtocol::Quic(_) => TransportKind::Quic,
+            TransportProtocol::Ws(_) => TransportKind::Ws,
+            TransportProtocol::Wss(_) => TransportKind::Wss,
+            TransportProtocol::WebTransport(_) => TransportKind::WebTransport,
+        }
+    }
+
+    /// Returns the protocol segments expressing this transport, e.g. `/udp/443/quic`.
+    pub fn protocols(&self) -> Vec<Protocol> {
+        use TransportProtocol::*;
+        match *self {
+            Tcp(port) => vec![Protocol::Tcp(port)],
+            Udp(port) => vec![Protocol::Udp(port)],
+            TlsTcp(port) => vec![Protocol::Tcp(port), Protocol::Tls],
+            Quic(port) => vec![Protocol::Udp(port), Protocol::Quic],
+            Ws(port) => vec![Protocol::Tcp(port), Protocol::Ws(port)],
+            Wss(port) => vec![Protocol::Tcp(port), Protocol::Wss(port)],
+            WebTransport(port) => vec![Protocol::Udp(port), Protocol::WebTransport(port)],
+        }
+    }
+
     /// Check if the transport protocol is secure. (by TLS)
     pub fn is_secure(&self) -> bool {
         matches!(
@@ -137,6 +253,41 @@ impl TransportProtocol {
     }
 }
 
+/// The kind of a [`TransportProtocol`], without a port.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum TransportKind {
+    /// TCP
+    Tcp,
+    /// UDP
+    Udp,
+    /// TLS over TCP
+    TlsTcp,
+    /// QUIC over UDP
+    Quic,
+    /// WebSocket over TCP
+    Ws,
+    /// Secure WebSocket over TCP
+    Wss,
+    /// WebTransport over UDP
+    WebTransport,
+}
+
+impl TransportKind {
+    /// Returns the transport protocol of this kind on `port`.
+    pub fn with_port(self, port: u16) -> TransportProtocol {
+        match self {
+            TransportKind::Tcp => TransportProtocol::Tcp(port),
+            TransportKind::Udp => TransportProtocol::Udp(port),
+            TransportKind::TlsTcp => TransportProtocol::TlsTcp(port),
+            TransportKind::Quic => TransportProtocol::Quic(port),
+            TransportKind::Ws => TransportProtocol::Ws(port),
+            TransportKind::Wss => TransportProtocol::Wss(port),
+            TransportKind::WebTransport => TransportProtocol::WebTransport(port),
+        }
+    }
+}
+
 impl fmt::Display for TransportProtocol {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         use TransportProtocol::*;
@@ -156,11 +307,24 @@ impl fmt::Display for TransportProtocol {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_layer() {
+        assert_eq!(
+            Protocol::Mac(MacAddr::new(0, 0, 0, 0, 0, 0)).layer(),
+            Some(Layer::Link)
+        );
+        assert_eq!(Protocol::Dns4("a".into()).layer(), Some(Layer::Network));
+        assert_eq!(Protocol::Quic.layer(), Some(Layer::Transport));
+        assert_eq!(Protocol::Wss(443).layer(), Some(Layer::Application));
+        assert_eq!(Protocol::Custom("x".into()).layer(), None);
+        assert!(Layer::Link < Layer::Network && Layer::Transport < Layer::Application);
+    }
+
     #[test]
     fn test_display_macaddr() {
         use mac_addr::MacAddr;
         let mac = MacAddr::new(0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff);
-        let proto = vec![
+        let proto = [
             Protocol::Mac(mac),
             Protocol::Ip4("192.168.10.10".parse().unwrap()),
         ];
@@ -170,7 +334,7 @@ mod tests {
 
     #[test]
     fn test_display_ip4_quic() {
-        let proto = vec![
+        let proto = [
             Protocol::Ip4("127.0.0.1".parse().unwrap()),
             Protocol::Udp(4433),
             Protocol::Quic,
@@ -181,7 +345,7 @@ mod tests {
 
     #[test]
     fn test_display_ip6_tcp_https() {
-        let proto = vec![
+        let proto = [
             Protocol::Ip6("::1".parse().unwrap()),
             Protocol::Tcp(443),
             Protocol::Https,
@@ -0,0 +1,153 @@
+//! Serde support
+//!
+//! [`StackAddr`] serializes as its string form (e.g. `"/ip4/10.0.0.1/tcp/443"`) for
+//! human-readable formats such as JSON or YAML, and as its compact binary form
+//! (see [`codec`](crate::codec)) for binary formats.
+//!
+//! Deserializing from a human-readable format also accepts the structured form
+//! written by earlier versions (`{"segments":[{"Protocol":{"Ip4":"10.0.0.1"}}, ...]}`).
+
+use ::serde::{
+    Deserialize, Deserializer, Serialize, Serializer,
+    de::{self, MapAccess, SeqAccess, Visitor, value::MapAccessDeserializer},
+};
+use std::fmt;
+
+use crate::{addr::StackAddr, segment::Segment};
+
+/// Upper bound on the capacity reserved from a sequence's size hint, which comes
+/// from the input and cannot be trusted.
+const MAX_PREALLOC: usize = 4096;
+
+/// Collects a byte string that a format hands over as a sequence of integers.
+pub(crate) fn byte_seq<'de, A: SeqAccess<'de>>(mut seq: A) -> Result<Vec<u8>, A::Error> {
+    let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(MAX_PREALLOC));
+    while let Some(b) = seq.next_element::<u8>()? {
+        bytes.push(b);
+    }
+    Ok(bytes)
+}
+
+impl Serialize for StackAddr {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            serializer.serialize_bytes(&self.to_bytes())
+        }
+    }
+}
+
+/// The structured form produced by the derived implementation in earlier versions.
+#[derive(Deserialize)]
+struct Legacy {
+    segments: Vec<Segment>,
+}
+
+struct StackAddrVisitor;
+
+impl<'de> Visitor<'de> for StackAddrVisitor {
+    type Value = StackAddr;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a stack address string or its binary form")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<StackAddr, E> {
+        v.parse().map_err(E::custom)
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<StackAddr, E> {
+        StackAddr::from_bytes(v).map_err(E::custom)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<StackAddr, A::Error> {
+        // Some formats hand byte strings over as sequences of integers.
+        self.visit_bytes(&byte_seq(seq)?)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<StackAddr, A::Error> {
+        let legacy = Legacy::deserialize(MapAccessDeserializer::new(map))?;
+        Ok(StackAddr::new(legacy.segments))
+    }
+}
+
+impl<'de> Deserialize<'de> for StackAddr {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(StackAddrVisitor)
+        } else {
+            deserializer.deserialize_bytes(StackAddrVisitor)
+        }
+    }
+}
+
+/// Yields the given bytes while claiming to hold far more, like a malicious payload.
+#[cfg(test)]
+pub(crate) struct HugeHint(pub(crate) std::vec::IntoIter<u8>);
+
+#[cfg(test)]
+impl Iterator for HugeHint {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        self.0.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, Some(usize::MAX))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_human_readable_string() {
+        let addr: StackAddr = "/ip4/10.0.0.1/tcp/443/tls/http/meta/env/prod"
+            .parse()
+            .unwrap();
+        let json = serde_json::to_string(&addr).unwrap();
+        assert_eq!(json, r#""/ip4/10.0.0.1/tcp/443/tls/http/meta/env/prod""#);
+        assert_eq!(serde_json::from_str::<StackAddr>(&json).unwrap(), addr);
+
+        assert!(serde_json::from_str::<StackAddr>(r#""/ip4/1.2.3""#).is_err());
+    }
+
+    #[test]
+    fn test_legacy_structured_form() {
+        let json = r#"{"segments":[{"Protocol":{"Ip4":"10.0.0.1"}},{"Protocol":{"Tcp":443}},{"Path":"index.html"}]}"#;
+        let addr: StackAddr = serde_json::from_str(json).unwrap();
+        assert_eq!(addr.to_string(), "/ip4/10.0.0.1/tcp/443/index.html");
+    }
+
+    #[test]
+    fn test_binary_form() {
+        let addr: StackAddr =
+            "/dns/example.com/udp/4433/quic/uuid/550e8400-e29b-41d4-a716-446655440000"
+                .parse()
+                .unwrap();
+        let encoded = bincode::serialize(&addr).unwrap();
+        // Length prefix followed by the codec bytes.
+        assert_eq!(&encoded[8..], &addr.to_bytes()[..]);
+        assert_eq!(bincode::deserialize::<StackAddr>(&encoded).unwrap(), addr);
+    }
+
+    #[test]
+    fn test_seq_size_hint_is_capped() {
+        let addr: StackAddr = "/ip4/10.0.0.1/tcp/443".parse().unwrap();
+        let seq = de::value::SeqDeserializer::<_, de::value::Error>::new(HugeHint(
+            addr.to_bytes().to_vec().into_iter(),
+        ));
+        assert_eq!(seq.size_hint(), Some(usize::MAX));
+        let bytes = byte_seq(seq).unwrap();
+        assert!(bytes.capacity() <= MAX_PREALLOC);
+        assert_eq!(StackAddr::from_bytes(&bytes).unwrap(), addr);
+
+        let seq = de::value::SeqDeserializer::<_, de::value::Error>::new(HugeHint(
+            addr.to_bytes().to_vec().into_iter(),
+        ));
+        assert_eq!(StackAddrVisitor.visit_seq(seq).unwrap(), addr);
+    }
+}
@@ -0,0 +1,445 @@
+//! Signed address records (`crypto` feature)
+//!
+//! A [`SignedStackAddr`] bundles a set of addresses with a sequence number and an
+//! expiry time, signed by the key named in the addresses' identity segment. Peers
+//! can hand these records around (e.g. in a discovery layer) and anyone receiving
+//! one can check that the holder of the key really published those addresses.
+//!
+//! Signing goes through the [`AddrSigner`] trait, which is implemented for the
+//! signing keys of `ed25519-dalek`, `k256` and `p256`.
+//!
+//! ```rust
+//! use std::time::{Duration, SystemTime};
+//! use ed25519_dalek::SigningKey;
+//! use stackaddr::{AddrSigner, SignedStackAddr, StackAddr};
+//!
+//! let key = SigningKey::from_bytes(&[7; 32]);
+//! let addr: StackAddr = "/ip4/127.0.0.1/udp/4433/quic".parse().unwrap();
+//! let addr = addr.with_identity(key.identity());
+//!
+//! let expires = SystemTime::now() + Duration::from_secs(3600);
+//! let record = SignedStackAddr::sign(vec![addr], 1, expires, &key).unwrap();
+//!
+//! let received = SignedStackAddr::from_bytes(&record.to_bytes()).unwrap();
+//! assert!(received.verify().is_ok());
+//! ```
+
+use bytes::{Buf, Bytes, BytesMut};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::{
+    addr::StackAddr,
+    borrowed::SegmentRef,
+    codec,
+    error::StackAddrError,
+    segment::{Segment, identity::Identity},
+};
+
+/// Prefix of every signed payload, so that a record signature cannot be
+/// mistaken for a signature over anything else.
+const DOMAIN: &[u8] = b"stackaddr-record:";
+
+/// A key that can sign address records.
+pub trait AddrSigner {
+    /// Returns the identity matching the public half of the key.
+    fn identity(&self) -> Identity;
+
+    /// Sign `msg`, returning a signature that [`Identity::verify`] accepts.
+    fn sign(&self, msg: &[u8]) -> Result<Vec<u8>, StackAddrError>;
+}
+
+impl AddrSigner for ed25519_dalek::SigningKey {
+    fn identity(&self) -> Identity {
+        Identity::from(self.verifying_key())
+    }
+
+    fn sign(&self, msg: &[u8]) -> Result<Vec<u8>, StackAddrError> {
+        use ed25519_dalek::Signer;
+        Ok(Signer::sign(self, msg).to_vec())
+    }
+}
+
+impl AddrSigner for k256::ecdsa::SigningKey {
+    fn identity(&self) -> Identity {
+        Identity::from(*self.verifying_key())
+    }
+
+    fn sign(&self, msg: &[u8]) -> Result<Vec<u8>, StackAddrError> {
+        use k256::ecdsa::{Signature, signature::Signer};
+        let sig: Signature = self
+            .try_sign(msg)
+            .map_err(|e| StackAddrError::SignatureInvalid(e.to_string()))?;
+        Ok(sig.to_vec())
+    }
+}
+
+impl AddrSigner for p256::ecdsa::SigningKey {
+    fn identity(&self) -> Identity {
+        Identity::from(*self.verifying_key())
+    }
+
+    fn sign(&self, msg: &[u8]) -> Result<Vec<u8>, StackAddrError> {
+        use p256::ecdsa::{Signature, signature::Signer};
+        let sig: Signature = self
+            .try_sign(msg)
+            .map_err(|e| StackAddrError::SignatureInvalid(e.to_string()))?;
+        Ok(sig.to_vec())
+    }
+}
+
+/// A set of addresses signed by the key of their identity.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedStackAddr {
+    addrs: Vec<StackAddr>,
+    seq: u64,
+    /// Expiry as seconds since the Unix epoch.
+    expires: u64,
+    identity: Identity,
+    signature: Bytes,
+}
+
+impl SignedStackAddr {
+    /// Sign `addrs` with `signer`.
+    ///
+    /// Every address must carry the signer's identity as its identity segment.
+    /// `seq` should grow with every new record, so that receivers can discard stale ones.
+    /// The expiry is stored with a precision of one second.
+    pub fn sign(
+        addrs: Vec<StackAddr>,
+        seq: u64,
+        expires: SystemTime,
+        signer: &impl AddrSigner,
+    ) -> Result<Self, StackAddrError> {
+        let identity = signer.identity();
+        check_identities(&addrs, &identity)?;
+        let expires = expires
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let mut record = SignedStackAddr {
+            addrs,
+            seq,
+            expires,
+            identity,
+            signature: Bytes::new(),
+        };
+        record.signature = Bytes::from(signer.sign(&record.signed_payload())?);
+        Ok(record)
+    }
+
+    /// Returns the signed addresses.
+    pub fn addrs(&self) -> &[StackAddr] {
+        &self.addrs
+    }
+
+    /// Returns the sequence number.
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    /// Returns the time after which the record is no longer valid.
+    pub fn expires(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.expires)
+    }
+
+    /// Returns the identity of the signer.
+    pub fn identity(&self) -> &Identity {
+        &self.identity
+    }
+
+    /// Returns the raw signature.
+    pub fn signature(&self) -> &[u8] {
+        &self.signature
+    }
+
+    /// Check the signature, the signer and the expiry against the current time.
+    pub fn verify(&self) -> Result<(), StackAddrError> {
+        self.verify_at(SystemTime::now())
+    }
+
+    /// Check the record as of `now`.
+    ///
+    /// The signature must verify under the signer's identity, every address must
+    /// carry that identity (see [`StackAddr::get_identity`]), and `now` must be
+    /// before the expiry.
+    pub fn verify_at(&self, now: SystemTime) -> Result<(), StackAddrError> {
+        check_identities(&self.addrs, &self.identity)?;
+        self.identity
+            .verify(&self.signed_payload(), &self.signature)?;
+        if now >= self.expires() {
+            return Err(StackAddrError::RecordExpired);
+        }
+        Ok(())
+    }
+
+    /// Encode the record in binary form.
+    ///
+    /// The signature is appended to the fields it covers: the sequence number, the
+    /// expiry, the signer's identity and the addresses, each length-prefixed.
+    pub fn to_bytes(&self) -> Bytes {
+        let mut buf = self.encode_fields();
+        codec::put_len_prefixed(&mut buf, &self.signature);
+        buf.freeze()
+    }
+
+    /// Decode a record produced by [`SignedStackAddr::to_bytes`].
+    ///
+    /// This only checks the structure; call [`SignedStackAddr::verify`] before trusting it.
+    pub fn from_bytes(mut buf: &[u8]) -> Result<Self, StackAddrError> {
+        let seq = codec::get_varint(&mut buf)?;
+        let expires = check_expiry(codec::get_varint(&mut buf)?)?;
+        let identity = match codec::decode_segment_ref(&mut buf, None)? {
+            SegmentRef::Identity(id) => id.into_owned(),
+            _ => return Err(StackAddrError::InvalidEncoding("signer identity")),
+        };
+        let count = codec::get_varint(&mut buf)?;
+        // Every address takes at least one byte, which bounds the allocation below.
+        if count > buf.remaining() as u64 {
+            return Err(StackAddrError::InvalidEncoding("truncated value"));
+        }
+        let addrs = (0..count)
+            .map(|_| StackAddr::from_bytes(codec::get_len_prefixed(&mut buf)?))
+            .collect::<Result<Vec<_>, _>>()?;
+        let signature = Bytes::copy_from_slice(codec::get_len_prefixed(&mut buf)?);
+        if buf.has_remaining() {
+            return Err(StackAddrError::InvalidEncoding("trailing bytes"));
+        }
+        Ok(SignedStackAddr {
+            addrs,
+            seq,
+            expires,
+            identity,
+            signature,
+        })
+    }
+
+    fn encode_fields(&self) -> BytesMut {
+        let mut buf = BytesMut::new();
+        codec::put_varint(&mut buf, self.seq);
+        codec::put_varint(&mut buf, self.expires);
+        codec::encode_segment(&mut buf, &Segment::Identity(self.identity.clone()), None);
+        codec::put_varint(&mut buf, self.addrs.len() as u64);
+        for addr in &self.addrs {
+            codec::put_len_prefixed(&mut buf, &addr.to_bytes());
+        }
+        buf
+    }
+
+    fn signed_payload(&self) -> Vec<u8> {
+        [DOMAIN, &self.encode_fields()].concat()
+    }
+}
+
+/// Check that an expiry read from the wire is representable as a [`SystemTime`].
+fn check_expiry(secs: u64) -> Result<u64, StackAddrError> {
+    UNIX_EPOCH
+        .checked_add(Duration::from_secs(secs))
+        .map(|_| secs)
+        .ok_or(StackAddrError::InvalidEncoding("expiry out of range"))
+}
+
+fn check_identities(addrs: &[StackAddr], identity: &Identity) -> Result<(), StackAddrError> {
+    match addrs.iter().find(|a| a.get_identity() != Some(identity)) {
+        Some(addr) => Err(StackAddrError::SignatureInvalid(format!(
+            "signer does not match the identity of {}",
+            addr
+        ))),
+        None => Ok(()),
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde_impl {
+    use ::serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+    use base64::Engine as _;
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use bytes::Bytes;
+
+    use super::SignedStackAddr;
+    use crate::addr::StackAddr;
+
+    /// The human-readable form, with the identity in its string form and the
+    /// signature in URL-safe base64.
+    #[derive(Serialize, Deserialize)]
+    struct Record {
+        addrs: Vec<StackAddr>,
+        seq: u64,
+        expires: u64,
+        identity: String,
+        signature: String,
+    }
+
+    impl Serialize for SignedStackAddr {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            if serializer.is_human_readable() {
+                Record {
+                    addrs: self.addrs.clone(),
+                    seq: self.seq,
+                    expires: self.expires,
+                    identity: self.identity.to_string(),
+                    signature: URL_SAFE_NO_PAD.encode(&self.signature),
+                }
+                .serialize(serializer)
+            } else {
+                serializer.serialize_bytes(&self.to_bytes())
+            }
+        }
+    }
+
+    impl<'de> Deserialize<'de> for SignedStackAddr {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            if deserializer.is_human_readable() {
+                let record = Record::deserialize(deserializer)?;
+                let identity = record
+                    .identity
+                    .parse::<StackAddr>()
+                    .map_err(de::Error::custom)?;
+                let identity = match identity.segments() {
+                    [crate::Segment::Identity(id)] => id.clone(),
+                    _ => return Err(de::Error::custom("expected a single identity segment")),
+                };
+                let signature = URL_SAFE_NO_PAD
+                    .decode(&record.signature)
+                    .map_err(de::Error::custom)?;
+                Ok(SignedStackAddr {
+                    addrs: record.addrs,
+                    seq: record.seq,
+                    expires: super::check_expiry(record.expires).map_err(de::Error::custom)?,
+                    identity,
+                    signature: Bytes::from(signature),
+                })
+            } else {
+                let bytes = byte_buf(deserializer)?;
+                SignedStackAddr::from_bytes(&bytes).map_err(de::Error::custom)
+            }
+        }
+    }
+
+    pub(super) fn byte_buf<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<u8>, D::Error> {
+        struct BytesVisitor;
+
+        impl<'de> de::Visitor<'de> for BytesVisitor {
+            type Value = Vec<u8>;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str("a signed address record in binary form")
+            }
+
+            fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Vec<u8>, E> {
+                Ok(v.to_vec())
+            }
+
+            fn visit_seq<A: de::SeqAccess<'de>>(self, seq: A) -> Result<Vec<u8>, A::Error> {
+                crate::serde::byte_seq(seq)
+            }
+        }
+
+        deserializer.deserialize_bytes(BytesVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(key: &impl AddrSigner) -> StackAddr {
+        let addr: StackAddr = "/ip4/192.0.2.1/udp/4433/quic".parse().unwrap();
+        addr.with_identity(key.identity())
+    }
+
+    fn expires() -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(2_000_000_000)
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let key = ed25519_dalek::SigningKey::from_bytes(&[7; 32]);
+        let record = SignedStackAddr::sign(vec![addr(&key)], 3, expires(), &key).unwrap();
+        assert_eq!(record.seq(), 3);
+        assert_eq!(record.expires(), expires());
+
+        let before = UNIX_EPOCH + Duration::from_secs(1_900_000_000);
+        assert!(record.verify_at(before).is_ok());
+        assert_eq!(
+            record.verify_at(expires()),
+            Err(StackAddrError::RecordExpired)
+        );
+
+        let decoded = SignedStackAddr::from_bytes(&record.to_bytes()).unwrap();
+        assert_eq!(decoded, record);
+        assert!(decoded.verify_at(before).is_ok());
+
+        // Any change to the signed fields breaks the signature.
+        let mut tampered = record.clone();
+        tampered.seq = 4;
+        assert!(matches!(
+            tampered.verify_at(before),
+            Err(StackAddrError::SignatureInvalid(_))
+        ));
+    }
+
+    #[test]
+    fn test_signer_must_match_identity() {
+        let key = ed25519_dalek::SigningKey::from_bytes(&[7; 32]);
+        let other = k256::ecdsa::SigningKey::from_slice(&[7; 32]).unwrap();
+        assert!(SignedStackAddr::sign(vec![addr(&other)], 1, expires(), &key).is_err());
+
+        let plain: StackAddr = "/ip4/192.0.2.1/tcp/80".parse().unwrap();
+        assert!(SignedStackAddr::sign(vec![plain], 1, expires(), &key).is_err());
+
+        // Swapping in another key's addresses and identity fails verification.
+        let mut record = SignedStackAddr::sign(vec![addr(&key)], 1, expires(), &key).unwrap();
+        record.addrs = vec![addr(&other)];
+        record.identity = other.identity();
+        assert!(record.verify_at(UNIX_EPOCH).is_err());
+    }
+
+    #[test]
+    fn test_ecdsa_signers() {
+        let k1 = k256::ecdsa::SigningKey::from_slice(&[7; 32]).unwrap();
+        let record = SignedStackAddr::sign(vec![addr(&k1)], 1, expires(), &k1).unwrap();
+        assert!(record.verify_at(UNIX_EPOCH).is_ok());
+
+        let p = p256::ecdsa::SigningKey::from_slice(&[7; 32]).unwrap();
+        let record = SignedStackAddr::sign(vec![addr(&p)], 1, expires(), &p).unwrap();
+        assert!(record.verify_at(UNIX_EPOCH).is_ok());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let key = ed25519_dalek::SigningKey::from_bytes(&[7; 32]);
+        let record = SignedStackAddr::sign(vec![addr(&key)], 1, expires(), &key).unwrap();
+
+        let json = serde_json::to_value(&record).unwrap();
+        assert_eq!(json["seq"], 1);
+        assert_eq!(json["addrs"][0], record.addrs()[0].to_string());
+        assert!(json["identity"].as_str().unwrap().starts_with("/ed25519/"));
+        let decoded: SignedStackAddr = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded, record);
+
+        let encoded = bincode::serialize(&record).unwrap();
+        assert_eq!(&encoded[8..], &record.to_bytes()[..]);
+        assert_eq!(
+            bincode::deserialize::<SignedStackAddr>(&encoded).unwrap(),
+            record
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_seq_size_hint_is_capped() {
+        use ::serde::de::value::{Error, SeqDeserializer};
+
+        let key = ed25519_dalek::SigningKey::from_bytes(&[7; 32]);
+        let record = SignedStackAddr::sign(vec![addr(&key)], 1, expires(), &key).unwrap();
+        let seq = SeqDeserializer::<_, Error>::new(crate::serde::HugeHint(
+            record.to_bytes().to_vec().into_iter(),
+        ));
+        let bytes = serde_impl::byte_buf(seq).unwrap();
+        assert!(bytes.capacity() < 64 * 1024);
+        assert_eq!(SignedStackAddr::from_bytes(&bytes).unwrap(), record);
+    }
+}
@@ -0,0 +1,274 @@
+//! URL interoperability
+//!
+//! Conversions between [`StackAddr`] and `http`, `https`, `ws` and `wss` URLs,
+//! available with the `url` feature.
+//!
+//! | URL                         | Stack address                                |
+//! |-----------------------------|----------------------------------------------|
+//! | `http://host:port`          | `/dns/host/tcp/<port>/http`                  |
+//! | `https://host:port`         | `/dns/host/tcp/<port>/tls/http`              |
+//! | `ws://host:port`            | `/dns/host/tcp/<port>/ws/<port>`             |
+//! | `wss://host:port`           | `/dns/host/tcp/<port>/wss/<port>`            |
+//!
+//! Missing ports default to the scheme's well-known port, and IP hosts become
+//! `/ip4` or `/ip6` segments. The URL path maps to path segments and query
+//! parameters to metadata. Fragments are dropped.
+//!
+//! When writing URLs, `/https` and HTTP/3 (`/udp/<port>/quic/http`) also map to
+//! `https`. Stacks without a URL equivalent, such as `/mac/...`, identities, or
+//! `/quic` without HTTP, are rejected with [`StackAddrError::NoEquivalent`].
+//!
+//! ```rust
+//! use stackaddr::StackAddr;
+//!
+//! let addr = StackAddr::from_url("https://example.com:8443/api/v1").unwrap();
+//! assert_eq!(addr.to_string(), "/dns/example.com/tcp/8443/tls/http/api/v1");
+//! assert_eq!(addr.to_url().unwrap().as_str(), "https://example.com:8443/api/v1");
+//! ```
+
+use ::url::{Host, Url};
+
+use crate::{
+    addr::StackAddr,
+    error::StackAddrError,
+    segment::{
+        Segment,
+        protocol::{Protocol, TransportKind},
+        unescape,
+    },
+};
+
+impl StackAddr {
+    /// Parse an `http`, `https`, `ws` or `wss` URL into a stack address (`url` feature).
+    ///
+    /// URLs carrying a username or password are rejected.
+    pub fn from_url(s: &str) -> Result<StackAddr, StackAddrError> {
+        let url = Url::parse(s).map_err(|e| StackAddrError::InvalidValue {
+            protocol: "url".to_string(),
+            raw: s.to_string(),
+            reason: e.to_string(),
+        })?;
+        let kind = match url.scheme() {
+            "http" => TransportKind::Tcp,
+            "https" => TransportKind::TlsTcp,
+            "ws" => TransportKind::Ws,
+            "wss" => TransportKind::Wss,
+            other => return Err(StackAddrError::NoEquivalent(format!("{} url", other))),
+        };
+        if !url.username().is_empty() || url.password().is_some() {
+            return Err(StackAddrError::NoEquivalent("url credentials".to_string()));
+        }
+        let port = url
+            .port_or_known_default()
+            .ok_or(StackAddrError::MissingPart("url port"))?;
+
+        let mut addr = match url.host() {
+            Some(Host::Domain(name)) => StackAddr::empty().with_dns_name(name),
+            Some(Host::Ipv4(ip)) => StackAddr::empty().with_ipv4(ip),
+            Some(Host::Ipv6(ip)) => StackAddr::empty().with_ipv6(ip),
+            None => return Err(StackAddrError::MissingPart("url host")),
+        };
+        addr = addr.with_transport(kind.with_port(port));
+        if matches!(kind, TransportKind::Tcp | TransportKind::TlsTcp) {
+            addr = addr.with_protocol(Protocol::Http);
+        }
+        for part in url.path_segments().into_iter().flatten() {
+            if !part.is_empty() {
+                addr.push(Segment::Path(unescape(part)?.into_owned()));
+            }
+        }
+        for (key, value) in url.query_pairs() {
+            addr.push(Segment::Metadata(key.into_owned(), value.into_owned()));
+        }
+        Ok(addr)
+    }
+
+    /// Convert the stack address into a URL (`url` feature).
+    ///
+    /// Fails with [`StackAddrError::NoEquivalent`] for stacks that no URL can express,
+    /// with [`StackAddrError::MissingPart`] when there is no host, and with
+    /// [`StackAddrError::InvalidValue`] when a DNS name is not a valid URL host.
+    pub fn to_url(&self) -> Result<Url, StackAddrError> {
+        let no_equivalent = |seg: &Segment| StackAddrError::NoEquivalent(seg.to_string());
+        let mut host = None;
+        let mut port = None;
+        let mut udp = false;
+        let mut secure = false;
+        let mut quic = false;
+        let mut scheme = None;
+        let mut paths = Vec::new();
+        let mut query = Vec::new();
+
+        for seg in self.segments() {
+            match seg {
+                Segment::Protocol(proto) if scheme.is_none() => match proto {
+                    Protocol::Ip4(ip) if host.is_none() => host = Some(Host::Ipv4(*ip)),
+                    Protocol::Ip6(ip) if host.is_none() => host = Some(Host::Ipv6(*ip)),
+                    Protocol::Dns(name) | Protocol::Dns4(name) | Protocol::Dns6(name)
+                        if host.is_none() =>
+                    {
+                        // Names are checked on their own, so that characters like `@` or
+                        // `/` cannot reach the authority of the URL.
+                        let word = match proto {
+                            Protocol::Dns4(_) => "dns4",
+                            Protocol::Dns6(_) => "dns6",
+                            _ => "dns",
+                        };
+                        let parsed =
+                            Host::parse(name).map_err(|e| StackAddrError::InvalidValue {
+                                protocol: word.to_string(),
+                                raw: name.clone(),
+                                reason: e.to_string(),
+                            })?;
+                        host = Some(parsed)
+                    }
+                    Protocol::Tcp(p) if port.is_none() => port = Some(*p),
+                    Protocol::Udp(p) if port.is_none() => {
+                        port = Some(*p);
+                        udp = true;
+                    }
+                    Protocol::Tls if port.is_some() && !udp && !secure => secure = true,
+                    Protocol::Quic if udp && !quic => quic = true,
+                    // Plain HTTP over TCP, or HTTP/3 over QUIC.
+                    Protocol::Http if udp == quic => {
+                        scheme = Some(if secure || quic { "https" } else { "http" })
+                    }
+                    Protocol::Https if !udp => scheme = Some("https"),
+                    Protocol::Ws(p) | Protocol::Wss(p)
+                        if !udp && port.is_none_or(|port| port == *p) =>
+                    {
+                        port = Some(*p);
+                        let wss = secure || matches!(proto, Protocol::Wss(_));
+                        scheme = Some(if wss { "wss" } else { "ws" });
+                    }
+                    _ => return Err(no_equivalent(seg)),
+                },
+                Segment::Path(path) if scheme.is_some() => paths.push(path.as_str()),
+                Segment::Metadata(key, value) if scheme.is_some() => query.push((key, value)),
+                _ => return Err(no_equivalent(seg)),
+            }
+        }
+
+        let host = host.ok_or(StackAddrError::MissingPart("ip or dns name"))?;
+        let Some(scheme) = scheme else {
+            return Err(StackAddrError::NoEquivalent(format!(
+                "{} without http or websocket",
+                self
+            )));
+        };
+        let mut url = Url::parse(&format!("{}://placeholder/", scheme))
+            .map_err(|e| StackAddrError::NoEquivalent(format!("{} url: {}", scheme, e)))?;
+        let host = host.to_string();
+        url.set_host(Some(&host))
+            .map_err(|e| StackAddrError::InvalidValue {
+                protocol: scheme.to_string(),
+                raw: host.clone(),
+                reason: e.to_string(),
+            })?;
+        url.set_port(port)
+            .map_err(|_| StackAddrError::NoEquivalent(format!("{} url port", scheme)))?;
+        if !paths.is_empty() {
+            url.path_segments_mut()
+                .map_err(|_| StackAddrError::NoEquivalent("url path".to_string()))?
+                .pop_if_empty()
+                .extend(paths);
+        }
+        if !query.is_empty() {
+            url.query_pairs_mut().extend_pairs(query);
+        }
+        Ok(url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_url_schemes() {
+        for (url, expected) in [
+            ("http://example.com", "/dns/example.com/tcp/80/http"),
+            ("https://example.com", "/dns/example.com/tcp/443/tls/http"),
+            ("ws://10.0.0.1:8080", "/ip4/10.0.0.1/tcp/8080/ws/8080"),
+            ("wss://[::1]/chat", "/ip6/::1/tcp/443/wss/443/chat"),
+        ] {
+            let addr = StackAddr::from_url(url).unwrap();
+            assert_eq!(addr.to_string(), expected);
+            assert_eq!(
+                addr.to_url().unwrap(),
+                Url::parse(url).unwrap(),
+                "{}",
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_url_query_and_path_escaping() {
+        let addr = StackAddr::from_url("http://example.com:8080/a%20b/tcp?k=v&x=1#frag").unwrap();
+        assert_eq!(
+            addr.to_string(),
+            "/dns/example.com/tcp/8080/http/a%20b/%74cp/meta/k/v/meta/x/1"
+        );
+        assert_eq!(
+            addr.to_url().unwrap().as_str(),
+            "http://example.com:8080/a%20b/tcp?k=v&x=1"
+        );
+    }
+
+    #[test]
+    fn test_to_url_variants() {
+        let cases = [
+            ("/dns/example.com/tcp/443/https", "https://example.com/"),
+            ("/ip4/10.0.0.1/udp/4433/quic/http", "https://10.0.0.1:4433/"),
+            ("/dns/example.com/http", "http://example.com/"),
+            ("/dns/example.com/tcp/80/tls/ws/80", "wss://example.com:80/"),
+        ];
+        for (addr, expected) in cases {
+            let addr: StackAddr = addr.parse().unwrap();
+            assert_eq!(addr.to_url().unwrap().as_str(), expected);
+        }
+    }
+
+    #[test]
+    fn test_to_url_rejects_crafted_names() {
+        for addr in [
+            "/dns/good.com%40evil.com/tcp/80/http",
+            "/dns/evil.com%2Fx/tcp/80/http",
+            "/dns/a.com%23/tcp/8080/http",
+        ] {
+            let addr: StackAddr = addr.parse().unwrap();
+            assert!(
+                matches!(addr.to_url(), Err(StackAddrError::InvalidValue { ref protocol, .. }) if protocol == "dns"),
+                "{}",
+                addr
+            );
+        }
+        let addr: StackAddr = "/dns/Example.COM/tcp/8080/http".parse().unwrap();
+        assert_eq!(addr.to_url().unwrap().as_str(), "http://example.com:8080/");
+    }
+
+    #[test]
+    fn test_url_no_equivalent() {
+        for addr in [
+            "/mac/aa:bb:cc:dd:ee:ff",
+            "/ip4/10.0.0.1/udp/4433/quic",
+            "/ip4/10.0.0.1/tcp/80/ws/81",
+            "/ip4/10.0.0.1/tcp/80/http/uuid/550e8400e29b41d4a716446655440000",
+        ] {
+            let addr: StackAddr = addr.parse().unwrap();
+            assert!(matches!(
+                addr.to_url(),
+                Err(StackAddrError::NoEquivalent(_))
+            ));
+        }
+        assert!(matches!(
+            StackAddr::from_url("https://user:pw@example.com"),
+            Err(StackAddrError::NoEquivalent(_))
+        ));
+        assert!(matches!(
+            StackAddr::from_url("ftp://example.com"),
+            Err(StackAddrError::NoEquivalent(_))
+        ));
+    }
+}
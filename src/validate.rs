@@ -0,0 +1,289 @@
+//! Stack validation
+//!
+//! Checks that the protocol segments of a [`StackAddr`](crate::StackAddr) are layered
+//! sensibly, following the ordering described in the [`protocol`](crate::segment::protocol)
+//! module: link, then network, then transport, then application.
+//!
+//! Identity, path and metadata segments are not part of the layering, except that an
+//! identity must not be followed by further transport protocols.
+//! Custom and registered protocols are not checked.
+//!
+//! A link or network protocol that follows an identity or an application protocol
+//! starts a new stack, as produced by [`StackAddr::encapsulate`](crate::StackAddr::encapsulate)
+//! or by relay addresses, and is checked on its own:
+//! `/ip4/10.0.0.1/udp/4433/quic/node/<id>/ip4/192.168.1.2/tcp/80/http` is valid.
+
+use std::fmt;
+
+use crate::segment::{
+    Segment,
+    protocol::{Layer, Protocol},
+};
+
+/// A single layering problem found by [`StackAddr::validate`](crate::StackAddr::validate).
+///
+/// Each variant carries the index of the offending segment.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Violation {
+    /// A protocol belongs to a lower layer than one already seen, e.g. `/tcp/80/ip4/...`.
+    OutOfOrder { index: usize },
+    /// A transport protocol appears without a network layer (IP or DNS) below it.
+    MissingNetwork { index: usize },
+    /// A second network-layer protocol (IP, DNS or Unix socket path) appears in the stack.
+    MultipleNetwork { index: usize },
+    /// A second TCP or UDP port appears in the stack.
+    MultipleTransport { index: usize },
+    /// `/quic` does not sit on top of `/udp`.
+    QuicWithoutUdp { index: usize },
+    /// `/tls` does not sit on top of `/tcp`.
+    TlsWithoutTcp { index: usize },
+    /// An identity segment is followed by a transport protocol of the same stack.
+    IdentityBeforeTransport { index: usize },
+    /// `/ip6zone` is not directly followed by `/ip6`.
+    ZoneWithoutIp6 { index: usize },
+}
+
+impl Violation {
+    /// Returns the index of the offending segment.
+    pub fn index(&self) -> usize {
+        match self {
+            Violation::OutOfOrder { index }
+            | Violation::MissingNetwork { index }
+            | Violation::MultipleNetwork { index }
+            | Violation::MultipleTransport { index }
+            | Violation::QuicWithoutUdp { index }
+            | Violation::TlsWithoutTcp { index }
+            | Violation::IdentityBeforeTransport { index }
+            | Violation::ZoneWithoutIp6 { index } => *index,
+        }
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::OutOfOrder { index } => {
+                write!(f, "segment {}: lower layer after a higher layer", index)
+            }
+            Violation::MissingNetwork { index } => {
+                write!(f, "segment {}: transport without a network layer", index)
+            }
+            Violation::MultipleNetwork { index } => {
+                write!(f, "segment {}: more than one network layer", index)
+            }
+            Violation::MultipleTransport { index } => {
+                write!(f, "segment {}: more than one transport port", index)
+            }
+            Violation::QuicWithoutUdp { index } => {
+                write!(f, "segment {}: quic is not over udp", index)
+            }
+            Violation::TlsWithoutTcp { index } => {
+                write!(f, "segment {}: tls is not over tcp", index)
+            }
+            Violation::IdentityBeforeTransport { index } => {
+                write!(f, "segment {}: identity before network or transport", index)
+            }
+            Violation::ZoneWithoutIp6 { index } => {
+                write!(f, "segment {}: ip6zone is not followed by ip6", index)
+            }
+        }
+    }
+}
+
+/// Collects all layering violations in `segments`.
+pub(crate) fn validate(segments: &[Segment]) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    let mut highest = None;
+    let mut network = false;
+    let mut last_port: Option<&Protocol> = None;
+    let mut identity = None;
+
+    for (index, seg) in segments.iter().enumerate() {
+        let p = match seg {
+            Segment::Protocol(p) => p,
+            Segment::Identity(_) => {
+                identity.get_or_insert(index);
+                continue;
+            }
+            Segment::Path(_) | Segment::Metadata(..) => continue,
+        };
+        let Some(layer) = p.layer() else { continue };
+
+        // A new lower stack on top of an identity or application protocol is an
+        // encapsulated address, so the layering starts over.
+        if layer <= Layer::Network && (identity.is_some() || highest == Some(Layer::Application)) {
+            highest = None;
+            network = false;
+            last_port = None;
+            identity = None;
+        }
+
+        if highest.is_some_and(|h| layer < h) {
+            violations.push(Violation::OutOfOrder { index });
+        }
+        highest = highest.max(Some(layer));
+
+        if layer <= Layer::Transport
+            && let Some(index) = identity.take()
+        {
+            violations.push(Violation::IdentityBeforeTransport { index });
+        }
+
+        match p {
+            Protocol::Ip4(_)
+            | Protocol::Ip6(_)
+            | Protocol::Dns(_)
+            | Protocol::Dns4(_)
+            | Protocol::Dns6(_)
+            | Protocol::Unix(_) => {
+                if network {
+                    violations.push(Violation::MultipleNetwork { index });
+                }
+                network = true;
+            }
+            Protocol::Ip6Zone(_)
+                if !matches!(
+                    segments.get(index + 1),
+                    Some(Segment::Protocol(Protocol::Ip6(_)))
+                ) =>
+            {
+                violations.push(Violation::ZoneWithoutIp6 { index });
+            }
+            Protocol::Tcp(_) | Protocol::Udp(_) => {
+                if !network {
+                    violations.push(Violation::MissingNetwork { index });
+                }
+                if last_port.is_some() {
+                    violations.push(Violation::MultipleTransport { index });
+                }
+                last_port = Some(p);
+            }
+            Protocol::Ws(_) | Protocol::Wss(_) | Protocol::WebTransport(_)
+                if !network && last_port.is_none() =>
+            {
+                violations.push(Violation::MissingNetwork { index });
+            }
+            Protocol::Quic if !matches!(last_port, Some(Protocol::Udp(_))) => {
+                violations.push(Violation::QuicWithoutUdp { index });
+            }
+            Protocol::Tls if !matches!(last_port, Some(Protocol::Tcp(_))) => {
+                violations.push(Violation::TlsWithoutTcp { index });
+            }
+            _ => {}
+        }
+    }
+
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StackAddr;
+
+    fn check(s: &str) -> Vec<Violation> {
+        let addr: StackAddr = s.parse().unwrap();
+        validate(addr.segments())
+    }
+
+    #[test]
+    fn test_valid_stacks() {
+        for s in [
+            "/ip4/127.0.0.1/tcp/443/tls/http",
+            "/mac/aa:bb:cc:dd:ee:ff/ip4/192.168.1.1/tcp/8080",
+            "/ip6/::1/udp/4433/quic/wtr/4433",
+            "/dns/example.com/tcp/443/wss/443/images/logo.png",
+            "/ip4/127.0.0.1/ws/8080",
+            "/onion/abcdefghijklmnop:80/http",
+            "/ip4/10.0.0.1/udp/4433/quic/node/AEBAGBAF/meta/env/prod",
+            "/meta/env/prod",
+            "/ip6zone/eth0/ip6/fe80::1/udp/4433/quic",
+            "/unix/%2Frun%2Fagent.sock/http",
+        ] {
+            assert_eq!(check(s), vec![], "{}", s);
+        }
+    }
+
+    #[test]
+    fn test_transport_before_network() {
+        assert_eq!(
+            check("/tcp/80/ip4/1.2.3.4/quic"),
+            vec![
+                Violation::MissingNetwork { index: 0 },
+                Violation::OutOfOrder { index: 1 },
+                Violation::QuicWithoutUdp { index: 2 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tls_over_udp() {
+        assert_eq!(
+            check("/tls/udp/53"),
+            vec![
+                Violation::TlsWithoutTcp { index: 0 },
+                Violation::MissingNetwork { index: 1 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_multiple_network_and_transport() {
+        assert_eq!(
+            check("/ip4/1.2.3.4/ip6/::1/tcp/80/udp/53"),
+            vec![
+                Violation::MultipleNetwork { index: 1 },
+                Violation::MultipleTransport { index: 3 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_identity_before_transport() {
+        assert_eq!(
+            check("/ip4/1.2.3.4/node/AEBAGBAF/tcp/80"),
+            vec![Violation::IdentityBeforeTransport { index: 1 }]
+        );
+    }
+
+    #[test]
+    fn test_encapsulated_stacks() {
+        let relay: StackAddr = "/ip4/10.0.0.1/udp/4433/quic/node/AEBAGBAF".parse().unwrap();
+        let target: StackAddr = "/ip4/192.168.1.2/tcp/80/http".parse().unwrap();
+        assert_eq!(validate(relay.encapsulate(&target).segments()), vec![]);
+
+        let proxy: StackAddr = "/dns/proxy.example/tcp/443/tls/http".parse().unwrap();
+        assert_eq!(validate(proxy.encapsulate(&target).segments()), vec![]);
+        assert_eq!(
+            validate(proxy.encapsulate(&target).encapsulate(&relay).segments()),
+            vec![]
+        );
+
+        // Without an identity or application protocol in between, it is still one stack.
+        assert_eq!(
+            check("/ip4/10.0.0.1/udp/4433/ip4/192.168.1.2/tcp/80"),
+            vec![
+                Violation::OutOfOrder { index: 2 },
+                Violation::MultipleNetwork { index: 2 },
+                Violation::MultipleTransport { index: 3 },
+            ]
+        );
+        // The inner stack is checked on its own.
+        assert_eq!(
+            check("/ip4/10.0.0.1/tcp/80/http/ip4/192.168.1.2/quic"),
+            vec![Violation::QuicWithoutUdp { index: 4 }]
+        );
+    }
+
+    #[test]
+    fn test_zone_without_ip6() {
+        assert_eq!(
+            check("/ip6/fe80::1/ip6zone/eth0/tcp/80"),
+            vec![Violation::ZoneWithoutIp6 { index: 1 }]
+        );
+        assert_eq!(
+            check("/ip6zone/eth0/ip4/10.0.0.1"),
+            vec![Violation::ZoneWithoutIp6 { index: 0 }]
+        );
+    }
+}